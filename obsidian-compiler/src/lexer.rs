@@ -67,7 +67,7 @@ impl Lexer {
                 if self.ch.is_alphabetic() || self.ch == '_' {
                     let ident = self.read_identifier();
                     return self.lookup_ident(&ident);
-                } else if self.ch.is_digit(10) {
+                } else if self.ch.is_ascii_digit() {
                     return Token::Int(self.read_number());
                 } else {
                     Token::Illegal
@@ -100,7 +100,7 @@ impl Lexer {
     // Reads a sequence of digits to form a number.
     fn read_number(&mut self) -> i64 {
         let start_pos = self.position;
-        while self.ch.is_digit(10) {
+        while self.ch.is_ascii_digit() {
             self.read_char();
         }
         let s: String = self.input[start_pos..self.position].iter().collect();
@@ -40,7 +40,7 @@ impl Parser {
             statements: Vec::new(),
         };
 
-        while self.cur_token != Token::Eof {
+        while !self.cur_token_is(&Token::Eof) {
             if let Some(stmt) = self.parse_statement() {
                 program.statements.push(stmt);
             }
@@ -100,7 +100,7 @@ impl Parser {
         // token and calls the appropriate parsing function.
         let prefix_fn = self.get_prefix_fn(&self.cur_token);
 
-        prefix_fn.map(|f| f(self)).flatten()
+        prefix_fn.and_then(|f| f(self))
     }
     
     // Returns the function needed to parse an expression based on the token type.
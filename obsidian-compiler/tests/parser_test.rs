@@ -1,3 +1,6 @@
+// `Statement` only has a `Let` variant so far; the `else` arms are there for when it grows.
+#![allow(irrefutable_let_patterns)]
+
 use obsidian_compiler::{
     ast::{Expression, Node, Statement},
     lexer::Lexer,
@@ -19,7 +22,7 @@ let foobar = 838383;
     check_parser_errors(&parser);
     assert_eq!(program.statements.len(), 3, "program.statements does not contain 3 statements.");
 
-    let expected_identifiers = vec!["x", "y", "foobar"];
+    let expected_identifiers = ["x", "y", "foobar"];
 
     for (i, expected_ident) in expected_identifiers.iter().enumerate() {
         let stmt = &program.statements[i];
@@ -0,0 +1,86 @@
+use crate::crypto::PublicKey;
+use crate::p2p::{ChainId, DEFAULT_CHAIN_ID};
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+// Human-readable addresses
+//
+// An address is the Bech32m encoding of a 32-byte public key. The
+// human-readable prefix names the chain, so an address meant for one network
+// is refused by another: the default chain uses `zel` (addresses start with
+// `zel1`), any other chain `zel<chain id>`. The checksum catches typos.
+
+// The prefix every chain's human-readable part starts with.
+pub const ADDRESS_PREFIX: &str = "zel";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AddressError {
+    #[error("Invalid address: {0}")]
+    Invalid(String),
+    #[error("Address is for network {found:?}, expected {expected:?}")]
+    WrongNetwork { expected: String, found: String },
+    #[error("Address holds {0} bytes, expected 32")]
+    WrongLength(usize),
+}
+
+/// The human-readable part of addresses on `chain_id`.
+pub fn hrp(chain_id: ChainId) -> String {
+    if chain_id == DEFAULT_CHAIN_ID {
+        ADDRESS_PREFIX.to_string()
+    } else {
+        format!("{}{}", ADDRESS_PREFIX, chain_id)
+    }
+}
+
+/// Encodes `key` as an address on `chain_id`.
+pub fn encode(key: &PublicKey, chain_id: ChainId) -> String {
+    let hrp = Hrp::parse(&hrp(chain_id)).expect("Address prefixes are valid HRPs");
+    bech32::encode::<Bech32m>(hrp, &key.0).expect("A 32-byte key fits in an address")
+}
+
+/// Decodes an address, refusing it unless it is well-formed, has a valid
+/// Bech32m checksum and belongs to `chain_id`.
+pub fn decode(address: &str, chain_id: ChainId) -> Result<PublicKey, AddressError> {
+    let checked = CheckedHrpstring::new::<Bech32m>(address).map_err(|e| AddressError::Invalid(e.to_string()))?;
+
+    let expected = hrp(chain_id);
+    let found = checked.hrp().to_lowercase();
+    if found != expected {
+        return Err(AddressError::WrongNetwork { expected, found });
+    }
+
+    let bytes: Vec<u8> = checked.byte_iter().collect();
+    let key: [u8; 32] = bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| AddressError::WrongLength(bytes.len()))?;
+    Ok(PublicKey(key))
+}
+
+impl PublicKey {
+    pub fn to_address(&self, chain_id: ChainId) -> String {
+        encode(self, chain_id)
+    }
+
+    pub fn from_address(address: &str, chain_id: ChainId) -> Result<Self, AddressError> {
+        decode(address, chain_id)
+    }
+}
+
+// Displays the key as an address on the default chain.
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(self, DEFAULT_CHAIN_ID))
+    }
+}
+
+// Parses an address on the default chain. Use `PublicKey::from_address` for other chains.
+impl FromStr for PublicKey {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode(s, DEFAULT_CHAIN_ID)
+    }
+}
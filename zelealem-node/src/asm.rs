@@ -0,0 +1,345 @@
+use crate::bytecode::{self, collection, OpCode};
+use crate::module::{entry, Module, ModuleError, MAGIC, MAX_CONSTANTS};
+use crate::zvm::{DEFAULT_MAX_STACK, MAX_BYTES_LEN};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use thiserror::Error;
+
+// The textual assembly format for ZVM bytecode. One instruction per line,
+// written as the opcode's name in snake_case followed by its operand, if any:
+//
+//     ; Doubles 21 in a function.
+//     .max_stack 8             ; directives make the output a module
+//     .entry spend main
+//     .const greeting "hello"
+//     main:
+//         push 21
+//         call double
+//         halt
+//     double:
+//         dup
+//         add
+//         return
+//
+// Push takes an integer; jumps and calls a label or an offset; collection ops
+// a collection name (`inputs`, `outputs`, `references`, `current`, `links`);
+// link_word `source` or `target`; push_bytes a string or `0x` hex; push_const a
+// constant name. Without any directive the output is a bare opcode stream.
+
+// Every way assembly source can be malformed, with the line it was found on.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    #[error("line {line}: unknown instruction `{word}`")]
+    UnknownInstruction { line: usize, word: String },
+    #[error("line {line}: unknown directive `{word}`")]
+    UnknownDirective { line: usize, word: String },
+    #[error("line {line}: `{word}` is missing an operand")]
+    MissingOperand { line: usize, word: String },
+    #[error("line {line}: unexpected `{word}`")]
+    UnexpectedOperand { line: usize, word: String },
+    #[error("line {line}: invalid operand `{word}`")]
+    InvalidOperand { line: usize, word: String },
+    #[error("line {line}: unknown label `{word}`")]
+    UnknownLabel { line: usize, word: String },
+    #[error("line {line}: `{word}` is defined twice")]
+    DuplicateName { line: usize, word: String },
+    #[error("line {line}: unterminated string")]
+    UnterminatedString { line: usize },
+    #[error("line {line}: a byte array of {len} bytes exceeds the limit of {max}")]
+    BytesTooLong { line: usize, len: usize, max: usize },
+    #[error("line {line}: a module may have at most {MAX_CONSTANTS} constants")]
+    TooManyConstants { line: usize },
+}
+
+// The names collections go by in assembly, by their immediate byte.
+const COLLECTIONS: [(&str, u8); 5] = [
+    ("inputs", collection::INPUTS),
+    ("outputs", collection::OUTPUTS),
+    ("references", collection::REFERENCES),
+    ("current", collection::CURRENT),
+    ("links", collection::LINKS),
+];
+// The names of LinkWord's link ends.
+const LINK_ENDS: [(&str, u8); 2] = [("source", 0), ("target", 1)];
+// The names of entry points in `.entry` directives, by their position.
+const ENTRY_POINTS: [(&str, usize); 2] = [("spend", entry::SPEND), ("link", entry::LINK)];
+
+/// The assembly name of an opcode: its name in snake_case, e.g. `jump_if`.
+pub fn mnemonic(opcode: OpCode) -> String {
+    let mut name = String::new();
+    for (i, c) in format!("{opcode:?}").chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+// Finds the opcode an assembly name refers to.
+fn opcode_named(word: &str) -> Option<OpCode> {
+    (0..=u8::MAX).filter_map(|byte| OpCode::try_from(byte).ok()).find(|opcode| mnemonic(*opcode) == word)
+}
+
+// An instruction's operand, kept as text until labels are known.
+struct Instruction<'s> {
+    line: usize,
+    opcode: OpCode,
+    operand: Option<&'s str>,
+}
+
+/// Assembles source text into validation logic: a bare opcode stream, or an
+/// encoded module if the source uses any directive.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut instructions = Vec::new();
+    let mut labels: HashMap<&str, u32> = HashMap::new();
+    let mut constants: Vec<(&str, Vec<u8>)> = Vec::new();
+    let mut entries: Vec<Option<(usize, &str)>> = vec![None; ENTRY_POINTS.len()];
+    let mut max_stack = None;
+    let mut is_module = false;
+    let mut offset = 0u32;
+
+    // First pass: lay the instructions out, noting where each label lands.
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut tokens = tokenize(text, line)?.into_iter().peekable();
+        while let Some(label) = tokens.peek().and_then(|token| token.strip_suffix(':')) {
+            if labels.insert(label, offset).is_some() {
+                return Err(AsmError::DuplicateName { line, word: label.to_string() });
+            }
+            tokens.next();
+        }
+        let Some(word) = tokens.next() else {
+            continue;
+        };
+        let operand = tokens.next();
+        let second_operand = tokens.next();
+        if let Some(extra) = tokens.next() {
+            return Err(AsmError::UnexpectedOperand { line, word: extra.to_string() });
+        }
+        let missing = || AsmError::MissingOperand { line, word: word.to_string() };
+
+        if let Some(directive) = word.strip_prefix('.') {
+            is_module = true;
+            let operand = operand.ok_or_else(missing)?;
+            match directive {
+                "max_stack" => max_stack = Some(parse_number(operand, line)?),
+                "entry" => {
+                    let (_, position) = lookup(&ENTRY_POINTS, operand, line)?;
+                    entries[position] = Some((line, second_operand.ok_or_else(missing)?));
+                    continue;
+                }
+                "const" => {
+                    if constants.iter().any(|(name, _)| *name == operand) {
+                        return Err(AsmError::DuplicateName { line, word: operand.to_string() });
+                    }
+                    if constants.len() == MAX_CONSTANTS {
+                        return Err(AsmError::TooManyConstants { line });
+                    }
+                    constants.push((operand, parse_bytes(second_operand.ok_or_else(missing)?, line, MAX_BYTES_LEN)?));
+                    continue;
+                }
+                _ => return Err(AsmError::UnknownDirective { line, word: word.to_string() }),
+            }
+            if let Some(extra) = second_operand {
+                return Err(AsmError::UnexpectedOperand { line, word: extra.to_string() });
+            }
+            continue;
+        }
+
+        if let Some(extra) = second_operand {
+            return Err(AsmError::UnexpectedOperand { line, word: extra.to_string() });
+        }
+        let opcode = opcode_named(word).ok_or_else(|| AsmError::UnknownInstruction { line, word: word.to_string() })?;
+        let mut len = 1 + opcode.immediate_len();
+        match (opcode.immediate_len(), operand) {
+            (0, Some(extra)) => return Err(AsmError::UnexpectedOperand { line, word: extra.to_string() }),
+            (0, None) => {}
+            (_, None) => return Err(missing()),
+            (_, Some(operand)) if opcode == OpCode::PushBytes => len += parse_bytes(operand, line, u8::MAX as usize)?.len(),
+            _ => {}
+        }
+        instructions.push(Instruction { line, opcode, operand });
+        offset += len as u32;
+    }
+
+    // Second pass: encode, now every label is known.
+    let mut code = Vec::with_capacity(offset as usize);
+    for Instruction { line, opcode, operand } in instructions {
+        code.push(opcode as u8);
+        let Some(operand) = operand else {
+            continue;
+        };
+        let invalid = || AsmError::InvalidOperand { line, word: operand.to_string() };
+        match opcode {
+            OpCode::Push => code.extend_from_slice(&parse_number::<i64>(operand, line)?.to_le_bytes()),
+            OpCode::Jump | OpCode::JumpIf | OpCode::Call => {
+                code.extend_from_slice(&resolve(&labels, operand, line)?.to_le_bytes())
+            }
+            OpCode::PushBytes => {
+                let bytes = parse_bytes(operand, line, u8::MAX as usize)?;
+                code.push(bytes.len() as u8);
+                code.extend_from_slice(&bytes);
+            }
+            OpCode::PushConst => {
+                let index = match constants.iter().position(|(name, _)| *name == operand) {
+                    Some(index) => index as u8,
+                    None => parse_number(operand, line)?,
+                };
+                code.push(index);
+            }
+            OpCode::LinkWord => code.push(lookup(&LINK_ENDS, operand, line).map(|(_, end)| end).or_else(|_| parse_number(operand, line))?),
+            OpCode::LoadLocal | OpCode::StoreLocal => code.push(parse_number(operand, line)?),
+            _ if opcode.immediate_len() == 1 => {
+                code.push(lookup(&COLLECTIONS, operand, line).map(|(_, which)| which).or_else(|_| parse_number(operand, line))?)
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    if !is_module {
+        return Ok(code);
+    }
+    // Without a spend entry point, execution starts at the top of the code.
+    let mut entry_points = Vec::new();
+    for (position, entry) in entries.into_iter().enumerate() {
+        match entry {
+            Some((line, label)) => entry_points.push(resolve(&labels, label, line)?),
+            None if position == entry::SPEND => entry_points.push(0),
+            None => break,
+        }
+    }
+    let module = Module {
+        max_stack: max_stack.unwrap_or(DEFAULT_MAX_STACK as u16),
+        entry_points,
+        constants: constants.into_iter().map(|(_, bytes)| bytes).collect(),
+        code,
+    };
+    Ok(module.encode())
+}
+
+/// Renders validation logic as assembly that `assemble` turns back into the same
+/// bytes. Jump targets and entry points get labels named after their offsets.
+pub fn disassemble(logic: &[u8]) -> Result<String, ModuleError> {
+    let module = Module::from_logic(logic)?;
+    let instructions = bytecode::decode_instructions(&module.code)?;
+    let starts: BTreeSet<usize> = instructions.iter().map(|(offset, _)| *offset).collect();
+    let label = |target: usize| format!("label_{target}");
+
+    let mut labelled: BTreeSet<usize> = instructions
+        .iter()
+        .filter(|(_, opcode)| opcode.is_jump())
+        .filter_map(|(offset, _)| bytecode::jump_target(&module.code, *offset))
+        .filter(|target| starts.contains(target))
+        .collect();
+
+    let mut text = String::new();
+    if logic.starts_with(&MAGIC) {
+        writeln!(text, ".max_stack {}", module.max_stack).unwrap();
+        for ((name, _), &entry) in ENTRY_POINTS.iter().zip(&module.entry_points) {
+            writeln!(text, ".entry {name} {}", label(entry as usize)).unwrap();
+            labelled.insert(entry as usize);
+        }
+        for (index, constant) in module.constants.iter().enumerate() {
+            writeln!(text, ".const c{index} 0x{}", hex::encode(constant)).unwrap();
+        }
+    }
+
+    for (offset, opcode) in instructions {
+        if labelled.contains(&offset) {
+            writeln!(text, "{}:", label(offset)).unwrap();
+        }
+        write!(text, "    {}", mnemonic(opcode)).unwrap();
+        let immediate = &module.code[offset + 1..];
+        let operand = match opcode {
+            OpCode::Push => Some(i64::from_le_bytes(immediate[..8].try_into().unwrap()).to_string()),
+            OpCode::Jump | OpCode::JumpIf | OpCode::Call => {
+                let target = bytecode::jump_target(&module.code, offset).unwrap();
+                Some(if starts.contains(&target) { label(target) } else { target.to_string() })
+            }
+            OpCode::PushBytes => Some(format!("0x{}", hex::encode(&immediate[1..1 + immediate[0] as usize]))),
+            OpCode::PushConst if (immediate[0] as usize) < module.constants.len() => Some(format!("c{}", immediate[0])),
+            OpCode::LinkWord => Some(name_or_number(&LINK_ENDS, immediate[0])),
+            OpCode::PushConst | OpCode::LoadLocal | OpCode::StoreLocal => Some(immediate[0].to_string()),
+            _ if opcode.immediate_len() == 1 => Some(name_or_number(&COLLECTIONS, immediate[0])),
+            _ => None,
+        };
+        if let Some(operand) = operand {
+            write!(text, " {operand}").unwrap();
+        }
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+// Splits a line into tokens, dropping its comment. A quoted string is one token, quotes included.
+fn tokenize(text: &str, line: usize) -> Result<Vec<&str>, AsmError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() && !rest.starts_with(';') {
+        let end = if let Some(quoted) = rest.strip_prefix('"') {
+            quoted.find('"').ok_or(AsmError::UnterminatedString { line })? + 2
+        } else {
+            rest.find(|c: char| c.is_whitespace() || c == ';').unwrap_or(rest.len())
+        };
+        tokens.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Ok(tokens)
+}
+
+// Parses a decimal or `0x` hexadecimal integer.
+fn parse_number<T: TryFrom<i128>>(word: &str, line: usize) -> Result<T, AsmError> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    };
+    value
+        .ok()
+        .map(|value| if negative { -value } else { value })
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| AsmError::InvalidOperand { line, word: word.to_string() })
+}
+
+// Parses a quoted string or `0x` hex into at most `max` bytes: PushBytes has a
+// one-byte length, while constants may be as long as any byte array.
+fn parse_bytes(word: &str, line: usize, max: usize) -> Result<Vec<u8>, AsmError> {
+    let bytes = if let Some(text) = word.strip_prefix('"').and_then(|word| word.strip_suffix('"')) {
+        text.as_bytes().to_vec()
+    } else {
+        word.strip_prefix("0x")
+            .and_then(|digits| hex::decode(digits).ok())
+            .ok_or_else(|| AsmError::InvalidOperand { line, word: word.to_string() })?
+    };
+    if bytes.len() > max {
+        return Err(AsmError::BytesTooLong { line, len: bytes.len(), max });
+    }
+    Ok(bytes)
+}
+
+// Resolves a jump target: a label, or a literal offset.
+fn resolve(labels: &HashMap<&str, u32>, word: &str, line: usize) -> Result<u32, AsmError> {
+    match labels.get(word) {
+        Some(offset) => Ok(*offset),
+        None => parse_number(word, line).map_err(|_| AsmError::UnknownLabel { line, word: word.to_string() }),
+    }
+}
+
+fn lookup<T: Copy>(names: &[(&'static str, T)], word: &str, line: usize) -> Result<(&'static str, T), AsmError> {
+    names
+        .iter()
+        .find(|(name, _)| *name == word)
+        .copied()
+        .ok_or_else(|| AsmError::InvalidOperand { line, word: word.to_string() })
+}
+
+fn name_or_number(names: &[(&str, u8)], byte: u8) -> String {
+    match names.iter().find(|(_, value)| *value == byte) {
+        Some((name, _)) => name.to_string(),
+        None => byte.to_string(),
+    }
+}
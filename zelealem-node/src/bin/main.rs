@@ -1,11 +1,15 @@
 // We need to use the zelealem_node library we've built.
 use zelealem_node::node::Node;
+use zelealem_node::config::{self, NodeConfig};
 use zelealem_node::consensus::Validator;
-use zelealem_node::ledger::Block;
-use zelealem_node::ledger::Transaction; 
+use zelealem_node::genesis::Genesis;
+use zelealem_node::rpc;
 use zelealem_node::topics; // New
-use zelealem_node::validator::TransactionValidator; // New
+use clap::Parser;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tokio::time::interval;
 
 // Import libp2p components needed for the main loop.
@@ -16,106 +20,241 @@ use libp2p::{
 };
 use tokio::select;
 use libp2p::futures::StreamExt;
+
+/// Runs a Zelealem node.
+/// Settings are read from the config file (if any) and then overridden by flags.
+#[derive(Parser, Debug)]
+#[command(name = "zelealem-node", version)]
+struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, short)]
+    config: Option<PathBuf>,
+    /// Directory for persistent node data, such as the libp2p identity.
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+    /// Genesis specification (TOML) to start the chain from.
+    #[arg(long = "genesis")]
+    genesis_file: Option<PathBuf>,
+    /// Multiaddress to listen on. May be repeated.
+    #[arg(long = "listen")]
+    listen_addrs: Vec<String>,
+    /// Multiaddress of a peer to dial on startup. May be repeated.
+    #[arg(long = "bootstrap")]
+    bootstrap_peers: Vec<String>,
+    /// Encrypted keystore holding the validator secret key.
+    #[arg(long)]
+    validator_key_file: Option<PathBuf>,
+    /// File holding the validator keystore password.
+    #[arg(long)]
+    validator_password_file: Option<PathBuf>,
+    /// Seconds between proposer ticks.
+    #[arg(long)]
+    block_time: Option<u64>,
+    /// Maximum number of transactions per proposed block.
+    #[arg(long)]
+    max_txs_per_block: Option<usize>,
+    /// Address for the JSON-RPC server (HTTP and WebSocket).
+    #[arg(long)]
+    rpc_addr: Option<SocketAddr>,
+    /// Disable the JSON-RPC server.
+    #[arg(long)]
+    no_rpc: bool,
+    /// Log filter, e.g. `info` or `zelealem_node=debug`.
+    #[arg(long)]
+    log_level: Option<String>,
+}
+
+impl Cli {
+    // Builds the effective configuration: file values first, then flag overrides.
+    fn into_config(self) -> Result<NodeConfig, config::ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => NodeConfig::load(path)?,
+            None => NodeConfig::default(),
+        };
+        if let Some(data_dir) = self.data_dir {
+            config.data_dir = data_dir;
+        }
+        if let Some(path) = self.genesis_file {
+            config.genesis_file = Some(path);
+        }
+        if !self.listen_addrs.is_empty() {
+            config.listen_addrs = self.listen_addrs;
+        }
+        if !self.bootstrap_peers.is_empty() {
+            config.bootstrap_peers = self.bootstrap_peers;
+        }
+        if let Some(path) = self.validator_key_file {
+            config.validator_key_file = Some(path);
+        }
+        if let Some(path) = self.validator_password_file {
+            config.validator_password_file = Some(path);
+        }
+        if let Some(block_time) = self.block_time {
+            config.block_time_secs = block_time;
+        }
+        if let Some(max_txs) = self.max_txs_per_block {
+            config.max_txs_per_block = max_txs;
+        }
+        if let Some(rpc_addr) = self.rpc_addr {
+            config.rpc_addr = rpc_addr;
+        }
+        if self.no_rpc {
+            config.rpc_enabled = false;
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_level = log_level;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
 // The `main` function of our executable.
 // It must be marked `#[tokio::main]` to run within the Tokio async runtime.
 #[tokio::main]
 async fn main() {
-    println!("Zelealem Node - Initializing...");
-
-    let mut node = Node::new().await;
-
-    // --- Manually set up a validator for testing ---
-    // In a real system, this would come from staking transactions.
-    // For now, we'll make our own node a validator.
-    // To get our specific PublicKey type, we must hash the public part of the id_keys.
-    // Get the PeerId from the node's swarm, which is the canonical public identity.
-    let local_peer_id = *node.swarm.local_peer_id();
-    // Convert the PeerId to bytes to create a hashable representation for our consensus.
-    let local_pub_key = zelealem_node::crypto::PublicKey(zelealem_node::crypto::hash_data(&local_peer_id.to_bytes()));
-    let validator = Validator {
-        pub_key: local_pub_key,
-        stake: 1000, // Stake 1000 units
+    let config = match Cli::parse().into_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = run(config).await {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(config: NodeConfig) -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::new().parse_filters(&config.log_level).init();
+    log::info!("Zelealem Node - Initializing...");
+
+    // Reuse the identity from previous runs so our PeerId stays stable.
+    let id_keys = config::load_or_generate_identity(&config.identity_path())?;
+    let genesis = config.genesis_file.as_deref().map(Genesis::load).transpose()?;
+    let mut node = match &genesis {
+        Some(genesis) => Node::with_genesis(id_keys, genesis).await?,
+        None => Node::with_identity(id_keys).await,
+    };
+    log::info!("Genesis block: {:?}", node.chain.get_latest_hash());
+
+    let local_pub_key = match &config.validator_key_file {
+        Some(path) => config::load_or_generate_validator_key(path, &config.validator_password()?)?.0,
+        None => {
+            // Get the PeerId from the node's swarm, which is the canonical public identity.
+            // Convert the PeerId to bytes to create a hashable representation for our consensus.
+            let local_peer_id = *node.swarm.local_peer_id();
+            zelealem_node::crypto::PublicKey(zelealem_node::crypto::hash_data(&local_peer_id.to_bytes()))
+        }
     };
-    node.validator_set.add_validator(validator);
-    println!("Local node registered as a validator.");
-    // ---------------------------------------------
 
-    node.swarm
-        .listen_on("/ip4/0.0.0.0/tcp/0".parse().unwrap())
-        .unwrap();
+    log::info!("Validator address: {}", local_pub_key.to_address(node.chain_id));
+
+    if genesis.is_none() {
+        // --- Without a genesis file, run a local chain with ourselves as the only validator ---
+        let validator = Validator {
+            pub_key: local_pub_key,
+            stake: 1000, // Stake 1000 units
+        };
+        node.validator_set.add_validator(validator);
+        log::info!("Local node registered as a validator.");
+    } else if node.validator_set.validators.contains_key(&local_pub_key) {
+        log::info!("Local node is a genesis validator.");
+    } else {
+        log::info!("Local node is not in the validator set; it will only relay and verify.");
+    }
 
-    let topic = gossipsub::IdentTopic::new("zelealem-blocks");
+    for addr in config.listen_multiaddrs()? {
+        if let Err(e) = node.swarm.listen_on(addr.clone()) {
+            log::error!("Failed to listen on {}: {}", addr, e);
+        }
+    }
+    for addr in config.bootstrap_multiaddrs()? {
+        log::info!("Dialing bootstrap peer {}", addr);
+        if let Err(e) = node.swarm.dial(addr.clone()) {
+            log::warn!("Failed to dial bootstrap peer {}: {}", addr, e);
+        }
+    }
+
+    let topic = topics::blocks_topic();
     node.swarm.behaviour_mut().gossipsub.subscribe(&topic).unwrap();
 
     let transactions_topic = topics::transactions_topic();
     node.swarm.behaviour_mut().gossipsub.subscribe(&transactions_topic).unwrap();
 
-    // Create a timer that fires every 10 seconds.
-    let mut proposer_tick = interval(Duration::from_secs(10));
+    // RPC handlers send queries here; the event loop below answers them.
+    let (rpc_query_tx, mut rpc_queries) = mpsc::channel::<rpc::NodeQuery>(256);
+    let _rpc_server = if config.rpc_enabled {
+        let ctx = rpc::RpcContext::new(rpc_query_tx.clone(), node.block_notifier.clone(), node.chain_id);
+        let (addr, handle) = rpc::start_server(config.rpc_addr, ctx).await?;
+        log::info!("JSON-RPC server listening on {}", addr);
+        Some(handle)
+    } else {
+        None
+    };
+
+    // Create a timer that fires once per block time.
+    let mut proposer_tick = interval(Duration::from_secs(config.block_time_secs));
 
-    println!("Node initialized. Listening for connections and proposing blocks...");
+    log::info!("Node initialized. Listening for connections and proposing blocks...");
 
     loop {
         select! {
-            // This branch fires every 10 seconds.
+            // This branch fires once per block time.
             _ = proposer_tick.tick() => {
-                println!("\n--- Proposer Tick ---");
+                log::debug!("--- Proposer Tick ---");
 
                 // Check if we are the chosen proposer for the current chain height.
                 let latest_hash = node.chain.get_latest_hash();
                 if let Some(chosen_proposer) = node.validator_set.select_proposer(latest_hash) {
-                    println!("Chosen proposer for this round: {:?}", chosen_proposer);
-                                        if chosen_proposer == local_pub_key {
-                        println!("It's our turn to propose a block!");
-                        
-                        // In a real node, we would collect transactions from a mempool.
-                        // For now, we create an empty block.
-                        // Pull a batch of transactions from the mempool.
-                        let transactions = node.mempool.get_batch(10); // Get up to 10 txs
-                        if !transactions.is_empty() {
-                            println!("Pulled {} transactions from mempool to include in new block.", transactions.len());
-                        }
+                    log::debug!("Chosen proposer for this round: {}", chosen_proposer.to_address(node.chain_id));
+                    if chosen_proposer == local_pub_key {
+                        log::info!("It's our turn to propose a block!");
 
-                        let new_block = Block::new(
-                            latest_hash,
-                            local_pub_key,
-                            transactions, // Add the transactions to the block
-                            vec![],       // No VDF proof for now
-                        );
+                        // Pull a batch of transactions from the mempool, keeping those still valid.
+                        let max_txs = config.max_txs_per_block.min(node.consensus_params.max_txs_per_block as usize);
+                        let new_block = node.propose_block(local_pub_key, max_txs);
+                        if !new_block.transactions.is_empty() {
+                            log::info!("Including {} transactions from the mempool in the new block.", new_block.transactions.len());
+                        }
                         let block_id_for_log = new_block.id; // Clone for logging before move
 
                         // 1. Process the new block locally.
                         // This updates our own chain and state database.
                         match node.process_block(new_block) {
                             Ok(_) => {
-                                println!("Successfully processed our own new block: {:?}", block_id_for_log);
-                                
+                                log::info!("Successfully processed our own new block: {:?}", block_id_for_log);
+
                                 // 2. Broadcast the block to the network.
                                 // We need to serialize the block to send it.
                                 // The block was moved into process_block, so we need to get it back.
                                 let last_block = node.chain.get_latest_block().unwrap();
-                                let serialized_block = bincode::serde::encode_to_vec(last_block, bincode::config::standard()).unwrap();
+                                let serialized_block = zelealem_node::p2p::encode_block(node.chain_id, last_block);
 
                                 if let Err(e) = node.swarm.behaviour_mut().gossipsub.publish(topic.clone(), serialized_block) {
-                                    println!("Error publishing block: {:?}", e);
+                                    log::warn!("Error publishing block: {:?}", e);
                                 } else {
-                                    println!("Successfully published new block to the network!");
+                                    log::info!("Successfully published new block to the network!");
                                 }
                             }
                             Err(e) => {
                                 // This should not happen if we create the block correctly.
-                                println!("Error processing our own block: {:?}", e);
+                                log::error!("Error processing our own block: {:?}", e);
                             }
                         }
                     }
                 }
             }
 
+            Some(query) = rpc_queries.recv() => {
+                query(&mut node);
+            }
+
             event = node.swarm.select_next_some() => {
-                // (This part is unchanged)
                 match event {
                     SwarmEvent::NewListenAddr { address, .. } => {
-                        println!("Node listening on: {}", address);
+                        log::info!("Node listening on: {}", address);
                     }
                     SwarmEvent::Behaviour(p2p_event) => {
                         match p2p_event {
@@ -123,53 +262,33 @@ async fn main() {
                                 match mdns_event {
                                     mdns::Event::Discovered(list) => {
                                         for (peer_id, _multiaddr) in list {
-                                            println!("mDNS discovered a new peer: {}", peer_id);
+                                            if node.peer_manager.is_banned(&peer_id) {
+                                                continue;
+                                            }
+                                            log::info!("mDNS discovered a new peer: {}", peer_id);
                                             node.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
                                         }
                                     },
                                     mdns::Event::Expired(list) => {
                                         for (peer_id, _multiaddr) in list {
-                                            println!("mDNS peer has expired: {}", peer_id);
+                                            log::info!("mDNS peer has expired: {}", peer_id);
                                             node.swarm.behaviour_mut().gossipsub.remove_explicit_peer(&peer_id);
                                         }
                                     }
                                 }
                             }
                             zelealem_node::p2p::ZelealemBehaviourEvent::Gossipsub(gossip_event) => {
-                                if let gossipsub::Event::Message { message, .. } = gossip_event {
-                                    // Check which topic the message arrived on.
-                                    if message.topic == topics::blocks_topic().hash() {
-                                        // TODO: Handle incoming blocks from other nodes
-                                        println!("Received new block via gossipsub.");
-                                    } else if message.topic == topics::transactions_topic().hash() {
-                                        println!("Received new transaction via gossipsub.");
-                                        // Try to deserialize the message data into a Transaction.
-                                        match bincode::serde::decode_from_slice::<Transaction, _>(&message.data, bincode::config::standard()) {
-                                            Ok((tx, _)) => {
-                                                println!("Successfully deserialized transaction: {:?}", tx.id);
-                                                // Validate the transaction against our current state.
-                                                let validator = TransactionValidator::new(&node.state_db);
-                                                match validator.validate_transaction(&tx) {
-                                                    Ok(_) => {
-                                                        println!("Transaction is valid! Adding to mempool.");
-                                                        // If valid, add it to our mempool.
-                                                        node.mempool.add_transaction(tx);
-                                                    }
-                                                    Err(e) => {
-                                                        println!("Invalid transaction received: {:?}", e);
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                println!("Failed to deserialize transaction: {:?}", e);
-                                            }
-                                        }
-                                    }
+                                if let gossipsub::Event::Message { propagation_source, message_id, message } = gossip_event {
+                                    // Validate the message (applying it if valid) and tell gossipsub the verdict,
+                                    // so invalid messages are not forwarded and their senders lose score.
+                                    let acceptance = node.handle_gossip_message(&message);
+                                    log::debug!("Gossip message {} from {}: {:?}", message_id, propagation_source, acceptance);
+                                    node.report_gossip_validation(&message_id, &propagation_source, acceptance);
                                 }
                             }
                             // inside the SwarmEvent::Behaviour match
                             zelealem_node::p2p::ZelealemBehaviourEvent::Ping(event) => {
-                                println!("Received ping event: {:?}", event);
+                                log::trace!("Received ping event: {:?}", event);
                             }
                         }
                     }
@@ -178,4 +297,4 @@ async fn main() {
             }
         }
     }
-}
\ No newline at end of file
+}
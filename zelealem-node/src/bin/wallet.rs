@@ -1,85 +1,409 @@
-use zelealem_node::{ledger::Transaction, topics, crypto};
-use libp2p::{
-    gossipsub, identity, noise, ping,
-    swarm::{NetworkBehaviour, SwarmEvent},
-    tcp, yamux, Multiaddr, SwarmBuilder,
+use clap::{Args, Parser, Subcommand};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use std::path::{Path, PathBuf};
+use zelealem_node::{
+    config,
+    crypto::{self, PublicKey},
+    keystore::Keystore,
+    ledger::{MultisigPolicy, StateObject, Transaction},
+    p2p::{ChainId, DEFAULT_CHAIN_ID},
+    rpc::{self, StateObjectView},
+    wallet::{self, PartialTransaction},
 };
-use tokio::time::{timeout, Duration};
-use libp2p::futures::StreamExt; // CORRECTED: Import the necessary trait
+
+// The file inside the wallet directory that holds the encrypted secret key.
+const KEY_FILE: &str = "keystore.json";
+
+// Environment variable the keystore password is read from when no password file is given.
+const PASSWORD_ENV: &str = "ZELEALEM_WALLET_PASSWORD";
+
+/// A command-line wallet for the Zelealem network.
+/// It talks to a node over JSON-RPC.
+#[derive(Parser, Debug)]
+#[command(name = "zelealem-wallet", version)]
+struct Cli {
+    /// URL of the node's JSON-RPC server.
+    #[arg(long, global = true, default_value = "http://127.0.0.1:9933")]
+    node: String,
+    /// Directory holding the wallet's key.
+    #[arg(long, global = true, default_value = "zelealem-wallet")]
+    wallet_dir: PathBuf,
+    /// File holding the keystore password. Without one, the password is read from
+    /// $ZELEALEM_WALLET_PASSWORD or prompted for.
+    #[arg(long, global = true)]
+    password_file: Option<PathBuf>,
+    /// Account to use, for wallets created from a mnemonic.
+    #[arg(long, global = true, default_value_t = 0)]
+    account: u32,
+    /// Chain the wallet's addresses belong to. Must match the node's.
+    #[arg(long, global = true, default_value_t = DEFAULT_CHAIN_ID)]
+    chain_id: ChainId,
+    #[command(subcommand)]
+    command: Command,
+}
+
+// An m-of-n key set, given on the command line as `--threshold M --key ADDR --key ADDR ...`.
+#[derive(Args, Debug)]
+struct MultisigArgs {
+    /// Number of keys that must sign.
+    #[arg(long, requires = "keys")]
+    threshold: Option<u8>,
+    /// Address of a key in the set. Repeat for each key.
+    #[arg(long = "key", requires = "threshold")]
+    keys: Vec<String>,
+}
+
+impl MultisigArgs {
+    fn policy(&self, chain_id: ChainId) -> CliResult<Option<MultisigPolicy>> {
+        let Some(threshold) = self.threshold else {
+            return Ok(None);
+        };
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| PublicKey::from_address(key, chain_id))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(MultisigPolicy::new(threshold, keys)?))
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a new wallet from a freshly generated mnemonic phrase.
+    New {
+        /// Overwrite an existing key.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Restore a wallet from its mnemonic phrase, read from standard input.
+    Restore {
+        /// Overwrite an existing key.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import an existing hex-encoded secret key as a single-account wallet.
+    ImportKey {
+        secret_key: String,
+        /// Overwrite an existing key.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the address of the selected account.
+    Address,
+    /// List the addresses of the first few accounts.
+    Accounts {
+        #[arg(long, default_value_t = 5)]
+        count: u32,
+    },
+    /// Print the address of an m-of-n multisig key set, to receive funds into.
+    MultisigAddress {
+        #[command(flatten)]
+        multisig: MultisigArgs,
+    },
+    /// List the State Objects this wallet owns.
+    Objects,
+    /// Print the total value this wallet owns.
+    Balance,
+    /// Build an unsigned transfer, with the objects it spends, for offline signing.
+    /// With `--threshold` and `--key`, spends from that multisig instead of the wallet's account.
+    Export {
+        /// Recipient address.
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u64,
+        #[command(flatten)]
+        multisig: MultisigArgs,
+        /// Write the partial transaction here instead of to standard output.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Review and sign an exported transaction. Needs no connection to a node.
+    Sign {
+        /// File holding the partial transaction.
+        file: PathBuf,
+        /// Write the signed transaction here instead of to standard output.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Sign without asking for confirmation.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Submit a signed transaction to the node.
+    Import {
+        /// File holding the signed partial transaction.
+        file: PathBuf,
+    },
+    /// Build, sign and submit a transfer in one step.
+    Send {
+        /// Recipient address.
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u64,
+    },
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
 
 #[tokio::main]
 async fn main() {
-    // === 1. SETUP: A behaviour with gossipsub AND ping ===
-    let id_keys = identity::Keypair::generate_ed25519();
-    
-    #[derive(NetworkBehaviour)]
-    #[behaviour(to_swarm = "WalletBehaviourEvent")]
-    struct WalletBehaviour {
-        gossipsub: gossipsub::Behaviour,
-        ping: ping::Behaviour,
-    }
-
-    #[derive(Debug)]
-    enum WalletBehaviourEvent {
-        Gossipsub(gossipsub::Event),
-        Ping(ping::Event),
-    }
-    impl From<gossipsub::Event> for WalletBehaviourEvent { fn from(v: gossipsub::Event) -> Self { Self::Gossipsub(v) } }
-    impl From<ping::Event> for WalletBehaviourEvent { fn from(v: ping::Event) -> Self { Self::Ping(v) } }
-
-    let behaviour = WalletBehaviour {
-        gossipsub: gossipsub::Behaviour::new(
-            gossipsub::MessageAuthenticity::Signed(id_keys.clone()),
-            gossipsub::Config::default(),
-        ).unwrap(),
-        ping: ping::Behaviour::new(ping::Config::new()),
-    };
-    
-    let mut swarm = SwarmBuilder::with_existing_identity(id_keys)
-        .with_tokio()
-        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
-        .unwrap()
-        .with_behaviour(|_| behaviour)
-        .unwrap()
-        .build();
-
-    let tx_topic = topics::transactions_topic();
-    swarm.behaviour_mut().gossipsub.subscribe(&tx_topic).unwrap();
-
-    // === 2. DIAL AND WAIT FOR A PONG ===
-    let target_node_addr: Multiaddr = "/ip4/127.0.0.1/tcp/52585".parse().expect("Failed to parse address"); // <-- IMPORTANT: CHANGE THIS PORT
-    swarm.dial(target_node_addr.clone()).unwrap();
-    println!("Wallet client started. Dialing node and waiting for a pong...");
-
-    // Event loop to wait for a successful ping
-    if let Ok(_) = timeout(Duration::from_secs(10), async {
-        loop {
-            // CORRECTED: The path to a successful ping event is simpler now.
-            if let SwarmEvent::Behaviour(WalletBehaviourEvent::Ping(ping::Event { result: Ok(_), .. })) = swarm.select_next_some().await {
-                println!("Successfully pinged the node!");
-                return;
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let key_path = cli.wallet_dir.join(KEY_FILE);
+    let password = || password(cli.password_file.as_deref(), false);
+    let chain_id = cli.chain_id;
+
+    match cli.command {
+        Command::New { force } => {
+            refuse_overwrite(&key_path, force)?;
+            let mnemonic = crypto::generate_mnemonic();
+            let seed = crypto::mnemonic_to_seed(&mnemonic, "")?;
+            let keystore = Keystore::encrypt_seed(&seed, &new_password(cli.password_file.as_deref())?)?;
+            keystore.save(&key_path)?;
+            println!("Write down this recovery phrase and keep it safe; it is the only backup of your keys:\n");
+            println!("    {}\n", mnemonic);
+            println!("Account 0 address: {}", keystore.public_key.to_address(chain_id));
+        }
+        Command::Restore { force } => {
+            refuse_overwrite(&key_path, force)?;
+            eprintln!("Enter the recovery phrase:");
+            let mut mnemonic = String::new();
+            std::io::stdin().read_line(&mut mnemonic)?;
+            let seed = crypto::mnemonic_to_seed(mnemonic.trim(), "")?;
+            let keystore = Keystore::encrypt_seed(&seed, &new_password(cli.password_file.as_deref())?)?;
+            keystore.save(&key_path)?;
+            println!("Restored wallet. Account 0 address: {}", keystore.public_key.to_address(chain_id));
+        }
+        Command::ImportKey { secret_key, force } => {
+            refuse_overwrite(&key_path, force)?;
+            let secret_key = hex::decode(secret_key.trim())?;
+            if secret_key.len() != 32 {
+                return Err("Secret key must be 32 bytes".into());
             }
+            Keystore::encrypt(&secret_key, &new_password(cli.password_file.as_deref())?)?.save(&key_path)?;
+            println!("Imported key. Address: {}", crypto::public_key_from_secret(&secret_key).to_address(chain_id));
         }
-    }).await {
-        println!("Ping successful. Publishing transaction...");
-
-        // === 3. CREATE AND PUBLISH TRANSACTION ===
-        let (_tx_pub_key, tx_sec_key) = crypto::generate_keypair();
-        let mut tx = Transaction::new(vec![[0; 32]], vec![], vec![]);
-        let signature = crypto::sign_data(&tx.id, &tx_sec_key);
-        tx.sign(signature);
-        
-        let serialized_tx = bincode::serde::encode_to_vec(&tx, bincode::config::standard()).unwrap();
-        if let Err(e) = swarm.behaviour_mut().gossipsub.publish(tx_topic, serialized_tx) {
-            println!("Error publishing transaction: {:?}", e);
-        } else {
-            println!("Successfully published dummy transaction!");
+        Command::Address => {
+            println!("{}", address(&key_path, cli.account, password)?.to_address(chain_id));
         }
+        Command::Accounts { count } => {
+            let keystore = Keystore::load(&key_path)?;
+            let (_, secret) = keystore.decrypt(&password()?)?;
+            for account in 0..count {
+                match wallet::account_keypair(keystore.kind, &secret, account) {
+                    Ok((public_key, _)) => println!("{}  {}", account, public_key.to_address(chain_id)),
+                    Err(wallet::WalletError::NoSuchAccount(_)) => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Command::Objects => {
+            let public_key = address(&key_path, cli.account, password)?;
+            for so in owned_objects(&connect(&cli.node, chain_id).await?, public_key, chain_id).await? {
+                match so.amount() {
+                    Some(amount) => println!("{}  value {}", hex::encode(so.id), amount),
+                    None => println!("{}  data {}", hex::encode(so.id), hex::encode(&so.data)),
+                }
+            }
+        }
+        Command::Balance => {
+            let public_key = address(&key_path, cli.account, password)?;
+            let balance: u64 = connect(&cli.node, chain_id)
+                .await?
+                .request("zel_getBalance", rpc_params![public_key.to_address(chain_id)])
+                .await?;
+            println!("{}", balance);
+        }
+        Command::MultisigAddress { multisig } => {
+            let policy = multisig.policy(chain_id)?.ok_or("--threshold and --key are required")?;
+            println!("{}", policy.owner_key().to_address(chain_id));
+        }
+        Command::Export { to, amount, multisig, out } => {
+            let recipient = PublicKey::from_address(&to, chain_id)?;
+            let policy = multisig.policy(chain_id)?;
+            let owner = match &policy {
+                Some(policy) => policy.owner_key(),
+                None => address(&key_path, cli.account, password)?,
+            };
+            let owned = owned_objects(&connect(&cli.node, chain_id).await?, owner, chain_id).await?;
+            let mut tx = wallet::build_transfer(&owned, recipient, amount, owner)?;
+            if let Some(policy) = policy {
+                for index in 0..tx.inputs.len() {
+                    tx.set_policy(index, policy.clone());
+                }
+            }
+            let inputs = tx
+                .inputs
+                .iter()
+                .map(|id| owned.iter().find(|so| &so.id == id).cloned().expect("Inputs come from the owned objects"))
+                .collect();
+            write_output(out.as_deref(), &PartialTransaction::new(tx, inputs, chain_id)?.encode())?;
+        }
+        Command::Sign { file, out, yes } => {
+            let mut partial = PartialTransaction::decode(&read_input(&file)?)?;
+            if partial.chain_id != chain_id {
+                return Err(format!("Transaction is for chain {}, but the wallet is using chain {}", partial.chain_id, chain_id).into());
+            }
+            print_review(&partial);
+            if !yes && !confirm("Sign this transaction?")? {
+                return Err("Signing cancelled".into());
+            }
+            let (_, secret_key) = unlock(&key_path, cli.account, &password()?)?;
+            let signed = partial.sign(&secret_key)?;
+            eprintln!("Signed {} of {} inputs", signed.len(), partial.transaction.inputs.len());
+            write_output(out.as_deref(), &partial.encode())?;
+        }
+        Command::Import { file } => {
+            let tx = PartialTransaction::decode(&read_input(&file)?)?.finalize(chain_id)?;
+            let id = submit(&connect(&cli.node, chain_id).await?, &tx).await?;
+            println!("Submitted transaction {}", id);
+        }
+        Command::Send { to, amount } => {
+            let recipient = PublicKey::from_address(&to, chain_id)?;
+            let (public_key, secret_key) = unlock(&key_path, cli.account, &password()?)?;
+            let client = connect(&cli.node, chain_id).await?;
+            let owned = owned_objects(&client, public_key, chain_id).await?;
+            let mut tx = wallet::build_transfer(&owned, recipient, amount, public_key)?;
+            wallet::sign_transaction(&mut tx, &owned, &secret_key)?;
+            let id = submit(&client, &tx).await?;
+            println!("Submitted transaction {}", id);
+        }
+    }
+    Ok(())
+}
 
-    } else {
-        println!("Failed to ping the node within 10 seconds.");
+fn refuse_overwrite(key_path: &Path, force: bool) -> CliResult<()> {
+    if key_path.exists() && !force {
+        return Err(format!("{} already exists; pass --force to replace it", key_path.display()).into());
     }
+    Ok(())
+}
+
+// The selected account's public key. Account 0's key is stored in the clear, so it needs no password.
+fn address(key_path: &Path, account: u32, password: impl FnOnce() -> CliResult<String>) -> CliResult<PublicKey> {
+    let keystore = Keystore::load(key_path)?;
+    if account == 0 {
+        return Ok(keystore.public_key);
+    }
+    let (_, secret) = keystore.decrypt(&password()?)?;
+    Ok(wallet::account_keypair(keystore.kind, &secret, account)?.0)
+}
+
+// Decrypts the keystore and returns the selected account's keypair.
+fn unlock(key_path: &Path, account: u32, password: &str) -> CliResult<(PublicKey, Vec<u8>)> {
+    let keystore = Keystore::load(key_path)?;
+    let (_, secret) = keystore.decrypt(password)?;
+    Ok(wallet::account_keypair(keystore.kind, &secret, account)?)
+}
+
+// Reads the keystore password from the password file, the environment, or the terminal.
+fn password(password_file: Option<&Path>, confirm: bool) -> CliResult<String> {
+    if let Some(path) = password_file {
+        return Ok(config::read_password_file(path)?);
+    }
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+        return Err("Passwords do not match".into());
+    }
+    Ok(password)
+}
+
+fn new_password(password_file: Option<&Path>) -> CliResult<String> {
+    let password = password(password_file, true)?;
+    if password.is_empty() {
+        return Err("Refusing to encrypt the key with an empty password".into());
+    }
+    Ok(password)
+}
+
+fn read_input(path: &Path) -> CliResult<String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e).into())
+}
+
+fn write_output(path: Option<&Path>, text: &str) -> CliResult<()> {
+    match path {
+        Some(path) => std::fs::write(path, format!("{}\n", text)).map_err(|e| format!("Failed to write {}: {}", path.display(), e).into()),
+        None => {
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+fn describe(so: &StateObject, chain_id: ChainId) -> String {
+    match so.amount() {
+        Some(amount) => format!("{}  value {}", so.owner.to_address(chain_id), amount),
+        None => format!("{}  data {}", so.owner.to_address(chain_id), hex::encode(&so.data)),
+    }
+}
+
+// Shows what a transaction spends and creates, so it can be checked before signing.
+// Goes to standard error, keeping standard output for the signed transaction.
+fn print_review(partial: &PartialTransaction) {
+    eprintln!("Transaction {}", hex::encode(partial.transaction.id));
+    for (so, witness) in partial.input_objects.iter().zip(&partial.transaction.witnesses) {
+        eprintln!("  spends   {}", describe(so, partial.chain_id));
+        if let Some(policy) = &witness.policy {
+            eprintln!("           owned {}-of-{} by:", policy.threshold, policy.keys.len());
+            for key in &policy.keys {
+                eprintln!("             {}", key.to_address(partial.chain_id));
+            }
+        }
+        eprintln!("           {} signature(s) so far", witness.signatures.len());
+    }
+    for id in &partial.transaction.reference_inputs {
+        eprintln!("  reads    {}", hex::encode(id));
+    }
+    for so in &partial.transaction.outputs {
+        eprintln!("  creates  {}", describe(so, partial.chain_id));
+    }
+    for link in &partial.transaction.causal_links {
+        eprintln!("  links    {} -> {}", hex::encode(link.source_so_id), hex::encode(link.target_so_id));
+    }
+    let (value_in, value_out) = partial.value_in_out();
+    eprintln!("  value in {}, value out {}", value_in, value_out);
+}
+
+fn confirm(question: &str) -> CliResult<bool> {
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+// Connects to the node, making sure it is on the chain the wallet expects.
+async fn connect(url: &str, chain_id: ChainId) -> CliResult<HttpClient> {
+    let client = HttpClientBuilder::default().build(url)?;
+    let node_chain_id: ChainId = client.request("zel_chainId", rpc_params![]).await?;
+    if node_chain_id != chain_id {
+        return Err(format!("Node is on chain {}, but the wallet is using chain {}", node_chain_id, chain_id).into());
+    }
+    Ok(client)
+}
+
+async fn owned_objects(client: &HttpClient, owner: PublicKey, chain_id: ChainId) -> CliResult<Vec<StateObject>> {
+    let views: Vec<StateObjectView> = client
+        .request("zel_getObjectsByOwner", rpc_params![owner.to_address(chain_id)])
+        .await?;
+    let objects = views.iter().map(|view| view.to_state_object(chain_id)).collect::<Result<_, _>>()?;
+    Ok(objects)
+}
 
-    tokio::time::sleep(Duration::from_secs(1)).await;
-    println!("Wallet client exiting.");
-}
\ No newline at end of file
+async fn submit(client: &HttpClient, tx: &Transaction) -> CliResult<String> {
+    let id: String = client
+        .request("zel_submitTransaction", rpc_params![rpc::encode_transaction_hex(tx)])
+        .await?;
+    Ok(id)
+}
@@ -0,0 +1,63 @@
+use clap::{Parser, Subcommand};
+use std::io::Read;
+use std::path::PathBuf;
+use zelealem_node::asm;
+
+/// Assembles and disassembles ZVM validation logic.
+/// Bytecode is read and written as hex, as the node's RPC shows it.
+#[derive(Parser, Debug)]
+#[command(name = "zasm", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Assemble source into bytecode, printed as hex.
+    Asm {
+        /// File holding the source. Read from standard input if omitted.
+        path: Option<PathBuf>,
+    },
+    /// Disassemble hex bytecode into source.
+    Disasm {
+        /// File holding the hex. Read from standard input if omitted.
+        path: Option<PathBuf>,
+    },
+}
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    match cli.command {
+        Command::Asm { path } => {
+            let code = asm::assemble(&read_input(path)?)?;
+            println!("{}", hex::encode(code));
+        }
+        Command::Disasm { path } => {
+            let code = hex::decode(read_input(path)?.trim())?;
+            print!("{}", asm::disassemble(&code)?);
+        }
+    }
+    Ok(())
+}
+
+// Reads the whole of `path`, or standard input if there is none.
+fn read_input(path: Option<PathBuf>) -> CliResult<String> {
+    match path {
+        Some(path) => Ok(std::fs::read_to_string(path)?),
+        None => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
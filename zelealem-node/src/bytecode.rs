@@ -1,7 +1,9 @@
+use crate::zvm::VmError;
+
 // #[repr(u8)] tells the Rust compiler to represent this enum as a single, unsigned 8-bit integer (a byte).
 // This is crucial because our bytecode will be a stream of bytes.
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
     /// 0x00: Halts execution.
     Halt = 0x00,
@@ -11,16 +13,348 @@ pub enum OpCode {
     Add = 0x02,
     /// 0x03: Pops two values from the stack, subtracts the top from the second-to-top, and pushes the result.
     Sub = 0x03,
+    /// 0x04: Pushes the first 8 bytes of the causal-link source's data (little-endian, zero-padded) as an i64.
+    /// For a value object this is its amount. Fails if the script is not running as a link target.
+    LinkSource = 0x04,
+    /// 0x05: Pops an index and pushes the first 8 bytes of that reference input's data, like LinkSource.
+    Reference = 0x05,
+    /// 0x06: Pops two values and pushes their product.
+    Mul = 0x06,
+    /// 0x07: Pops two values and pushes the second-to-top divided by the top, rounding toward zero.
+    Div = 0x07,
+    /// 0x08: Pops two values and pushes the remainder of dividing the second-to-top by the top.
+    Mod = 0x08,
+    /// 0x09: Pops two values and pushes 1 if they are equal, 0 otherwise.
+    Eq = 0x09,
+    /// 0x0a: Pops two values and pushes 1 if the second-to-top is less than the top, 0 otherwise.
+    Lt = 0x0a,
+    /// 0x0b: Pops two values and pushes 1 if the second-to-top is greater than the top, 0 otherwise.
+    Gt = 0x0b,
+    /// 0x0c: Pops two values and pushes 1 if both are non-zero, 0 otherwise.
+    And = 0x0c,
+    /// 0x0d: Pops two values and pushes 1 if either is non-zero, 0 otherwise.
+    Or = 0x0d,
+    /// 0x0e: Pops a value and pushes 1 if it is zero, 0 otherwise.
+    Not = 0x0e,
+    /// 0x0f: Pops two values and pushes their bitwise AND.
+    BitAnd = 0x0f,
+    /// 0x10: Pops two values and pushes their bitwise OR.
+    BitOr = 0x10,
+    /// 0x11: Pops two values and pushes their bitwise XOR.
+    BitXor = 0x11,
+    /// 0x12: Pops a value and pushes its bitwise complement.
+    BitNot = 0x12,
+    /// 0x13: Pops a shift amount (0 to 63) and a value, and pushes the value shifted left.
+    Shl = 0x13,
+    /// 0x14: Pops a shift amount (0 to 63) and a value, and pushes the value shifted right, keeping its sign.
+    Shr = 0x14,
+    /// 0x15: Continues execution at the code offset given by the next 4 bytes (little-endian u32).
+    Jump = 0x15,
+    /// 0x16: Pops a value and, if it is non-zero, jumps like Jump; otherwise continues after the target.
+    JumpIf = 0x16,
+    /// 0x17: Jumps like Jump, remembering where to come back to for the matching Return.
+    Call = 0x17,
+    /// 0x18: Returns to the instruction after the most recent Call.
+    Return = 0x18,
+    /// 0x19: Pushes a copy of the top value.
+    Dup = 0x19,
+    /// 0x1a: Swaps the top two values.
+    Swap = 0x1a,
+    /// 0x1b: Discards the top value.
+    Pop = 0x1b,
+    /// 0x1c: Pushes the local variable whose slot is given by the next byte.
+    LoadLocal = 0x1c,
+    /// 0x1d: Pops a value into the local variable whose slot is given by the next byte.
+    StoreLocal = 0x1d,
+    /// 0x1e: Pushes the number of items in the collection named by the next byte (see `collection`).
+    Count = 0x1e,
+    /// 0x1f: Pops an index and pushes the data length of that object in the collection named by the next byte.
+    DataLen = 0x1f,
+    /// 0x20: Pops a byte offset and an index, and pushes the 8 bytes at that offset of the object's data
+    /// (little-endian, zero-padded past the end). The next byte names the collection.
+    DataWord = 0x20,
+    /// 0x21: Like DataWord, but reads the object's 32-byte owner key.
+    OwnerWord = 0x21,
+    /// 0x22: Like DataWord, but reads the object's 32-byte id.
+    IdWord = 0x22,
+    /// 0x23: Pushes the position among the inputs of the object whose logic is running, or -1.
+    SelfIndex = 0x23,
+    /// 0x24: Pushes the height of the block the transaction is being validated for.
+    BlockHeight = 0x24,
+    /// 0x25: Pops a byte offset and a causal link index, and pushes 8 bytes of that link's source id
+    /// (next byte 0) or target id (next byte 1).
+    LinkWord = 0x25,
+    /// 0x26: Pushes a byte array given inline: the next byte is its length, followed by its bytes.
+    PushBytes = 0x26,
+    /// 0x27: Pops two byte arrays and pushes the second-to-top followed by the top.
+    Concat = 0x27,
+    /// 0x28: Pops a length, a start offset and a byte array, and pushes that range of it.
+    Slice = 0x28,
+    /// 0x29: Pops a byte array and pushes its length.
+    Len = 0x29,
+    /// 0x2a: Pops an integer and pushes its 8 little-endian bytes.
+    IntToBytes = 0x2a,
+    /// 0x2b: Pops a byte array of at most 8 bytes and pushes it read as a little-endian integer.
+    BytesToInt = 0x2b,
+    /// 0x2c: Pops an index and pushes the data of that object in the collection named by the next byte.
+    Data = 0x2c,
+    /// 0x2d: Like Data, but pushes the object's 32-byte owner key.
+    Owner = 0x2d,
+    /// 0x2e: Like Data, but pushes the object's 32-byte id.
+    Id = 0x2e,
+    /// 0x2f: Pops a byte array and pushes its 32-byte SHA-256 hash.
+    Sha256 = 0x2f,
+    /// 0x30: Pops a 32-byte public key and a 64-byte signature, and pushes 1 if the signature
+    /// over the transaction id is valid for that key, 0 otherwise.
+    VerifySignature = 0x30,
+    /// 0x31: Pops a 32-byte root, a leaf index, a proof of concatenated 32-byte sibling hashes
+    /// and a 32-byte leaf, and pushes 1 if the proof places the leaf under the root, 0 otherwise.
+    MerkleVerify = 0x31,
+    /// 0x32: Pushes the module constant whose index is given by the next byte, as a byte array.
+    PushConst = 0x32,
+}
+
+// The collections introspection opcodes can read from, named by their immediate byte.
+pub mod collection {
+    /// The objects the transaction spends, in order.
+    pub const INPUTS: u8 = 0;
+    /// The objects the transaction creates.
+    pub const OUTPUTS: u8 = 1;
+    /// The transaction's reference inputs.
+    pub const REFERENCES: u8 = 2;
+    /// The object whose logic is running, as a collection of one.
+    pub const CURRENT: u8 = 3;
+    /// The transaction's causal links; only valid for Count.
+    pub const LINKS: u8 = 4;
 }
 
-impl From<u8> for OpCode {
-    fn from(byte: u8) -> Self {
+// The size of a jump target immediate.
+pub const JUMP_TARGET_LEN: usize = 4;
+
+impl OpCode {
+    /// How many bytes of immediate operand follow this opcode in the code.
+    pub fn immediate_len(self) -> usize {
+        match self {
+            OpCode::Push => 8,
+            OpCode::Jump | OpCode::JumpIf | OpCode::Call => JUMP_TARGET_LEN,
+            OpCode::LoadLocal
+            | OpCode::StoreLocal
+            | OpCode::Count
+            | OpCode::DataLen
+            | OpCode::DataWord
+            | OpCode::OwnerWord
+            | OpCode::IdWord
+            | OpCode::LinkWord
+            | OpCode::Data
+            | OpCode::Owner
+            | OpCode::Id
+            | OpCode::PushConst => 1,
+            // The length byte; the bytes themselves follow it.
+            OpCode::PushBytes => 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether this opcode carries a jump target as its immediate.
+    pub fn is_jump(self) -> bool {
+        matches!(self, OpCode::Jump | OpCode::JumpIf | OpCode::Call)
+    }
+
+    /// How many values this opcode pops and then pushes. Call and Return move
+    /// between frames instead, so the verifier handles them itself.
+    pub fn stack_effect(self) -> (usize, usize) {
+        match self {
+            OpCode::Halt | OpCode::Pop | OpCode::StoreLocal | OpCode::JumpIf => (1, 0),
+            OpCode::Jump | OpCode::Call | OpCode::Return => (0, 0),
+            OpCode::Push
+            | OpCode::PushBytes
+            | OpCode::PushConst
+            | OpCode::LoadLocal
+            | OpCode::LinkSource
+            | OpCode::Count
+            | OpCode::SelfIndex
+            | OpCode::BlockHeight => (0, 1),
+            OpCode::Not
+            | OpCode::BitNot
+            | OpCode::Reference
+            | OpCode::DataLen
+            | OpCode::Len
+            | OpCode::IntToBytes
+            | OpCode::BytesToInt
+            | OpCode::Data
+            | OpCode::Owner
+            | OpCode::Id
+            | OpCode::Sha256 => (1, 1),
+            OpCode::Dup => (1, 2),
+            OpCode::Swap => (2, 2),
+            OpCode::Add
+            | OpCode::Sub
+            | OpCode::Mul
+            | OpCode::Div
+            | OpCode::Mod
+            | OpCode::Eq
+            | OpCode::Lt
+            | OpCode::Gt
+            | OpCode::And
+            | OpCode::Or
+            | OpCode::BitAnd
+            | OpCode::BitOr
+            | OpCode::BitXor
+            | OpCode::Shl
+            | OpCode::Shr
+            | OpCode::DataWord
+            | OpCode::OwnerWord
+            | OpCode::IdWord
+            | OpCode::LinkWord
+            | OpCode::Concat
+            | OpCode::VerifySignature => (2, 1),
+            OpCode::Slice => (3, 1),
+            OpCode::MerkleVerify => (4, 1),
+        }
+    }
+
+    /// The gas charged for executing this opcode. Opcodes that read State
+    /// Objects are additionally charged per byte read (see `zvm::GAS_PER_BYTE`).
+    pub fn gas_cost(self) -> u64 {
+        match self {
+            OpCode::Halt => 0,
+            OpCode::Dup | OpCode::Swap | OpCode::Pop => 2,
+            OpCode::Count | OpCode::SelfIndex | OpCode::BlockHeight => 2,
+            OpCode::DataLen | OpCode::DataWord | OpCode::OwnerWord | OpCode::IdWord | OpCode::LinkWord => 10,
+            // Ops producing byte arrays are also charged `GAS_PER_BYTE` for each byte produced.
+            OpCode::PushBytes | OpCode::PushConst | OpCode::Len | OpCode::IntToBytes | OpCode::BytesToInt => 3,
+            OpCode::Concat | OpCode::Slice => 5,
+            OpCode::Data | OpCode::Owner | OpCode::Id => 10,
+            // Intrinsics cost the same whatever their input, which `MAX_BYTES_LEN` bounds.
+            OpCode::Sha256 => 60,
+            OpCode::VerifySignature => 200,
+            OpCode::MerkleVerify => 400,
+            OpCode::Jump | OpCode::Return => 8,
+            OpCode::JumpIf | OpCode::Call => 10,
+            OpCode::Mul | OpCode::Div | OpCode::Mod => 5,
+            OpCode::Push
+            | OpCode::Add
+            | OpCode::Sub
+            | OpCode::Eq
+            | OpCode::Lt
+            | OpCode::Gt
+            | OpCode::And
+            | OpCode::Or
+            | OpCode::Not
+            | OpCode::BitAnd
+            | OpCode::BitOr
+            | OpCode::BitXor
+            | OpCode::BitNot
+            | OpCode::Shl
+            | OpCode::Shr
+            | OpCode::LoadLocal
+            | OpCode::StoreLocal => 3,
+            OpCode::LinkSource | OpCode::Reference => 20,
+        }
+    }
+}
+
+// Decoding a byte that is not an opcode is an error, not a panic: bytecode comes from untrusted peers.
+impl TryFrom<u8> for OpCode {
+    type Error = VmError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            0x00 => OpCode::Halt,
-            0x01 => OpCode::Push,
-            0x02 => OpCode::Add,
-            0x03 => OpCode::Sub,
-            _ => panic!("Invalid opcode: {}", byte),
+            0x00 => Ok(OpCode::Halt),
+            0x01 => Ok(OpCode::Push),
+            0x02 => Ok(OpCode::Add),
+            0x03 => Ok(OpCode::Sub),
+            0x04 => Ok(OpCode::LinkSource),
+            0x05 => Ok(OpCode::Reference),
+            0x06 => Ok(OpCode::Mul),
+            0x07 => Ok(OpCode::Div),
+            0x08 => Ok(OpCode::Mod),
+            0x09 => Ok(OpCode::Eq),
+            0x0a => Ok(OpCode::Lt),
+            0x0b => Ok(OpCode::Gt),
+            0x0c => Ok(OpCode::And),
+            0x0d => Ok(OpCode::Or),
+            0x0e => Ok(OpCode::Not),
+            0x0f => Ok(OpCode::BitAnd),
+            0x10 => Ok(OpCode::BitOr),
+            0x11 => Ok(OpCode::BitXor),
+            0x12 => Ok(OpCode::BitNot),
+            0x13 => Ok(OpCode::Shl),
+            0x14 => Ok(OpCode::Shr),
+            0x15 => Ok(OpCode::Jump),
+            0x16 => Ok(OpCode::JumpIf),
+            0x17 => Ok(OpCode::Call),
+            0x18 => Ok(OpCode::Return),
+            0x19 => Ok(OpCode::Dup),
+            0x1a => Ok(OpCode::Swap),
+            0x1b => Ok(OpCode::Pop),
+            0x1c => Ok(OpCode::LoadLocal),
+            0x1d => Ok(OpCode::StoreLocal),
+            0x1e => Ok(OpCode::Count),
+            0x1f => Ok(OpCode::DataLen),
+            0x20 => Ok(OpCode::DataWord),
+            0x21 => Ok(OpCode::OwnerWord),
+            0x22 => Ok(OpCode::IdWord),
+            0x23 => Ok(OpCode::SelfIndex),
+            0x24 => Ok(OpCode::BlockHeight),
+            0x25 => Ok(OpCode::LinkWord),
+            0x26 => Ok(OpCode::PushBytes),
+            0x27 => Ok(OpCode::Concat),
+            0x28 => Ok(OpCode::Slice),
+            0x29 => Ok(OpCode::Len),
+            0x2a => Ok(OpCode::IntToBytes),
+            0x2b => Ok(OpCode::BytesToInt),
+            0x2c => Ok(OpCode::Data),
+            0x2d => Ok(OpCode::Owner),
+            0x2e => Ok(OpCode::Id),
+            0x2f => Ok(OpCode::Sha256),
+            0x30 => Ok(OpCode::VerifySignature),
+            0x31 => Ok(OpCode::MerkleVerify),
+            0x32 => Ok(OpCode::PushConst),
+            _ => Err(VmError::InvalidOpcode(byte)),
+        }
+    }
+}
+
+/// Splits `code` into its instructions, returning each one's offset and opcode.
+/// Fails on an unknown opcode or an immediate cut off by the end of the code.
+pub fn decode_instructions(code: &[u8]) -> Result<Vec<(usize, OpCode)>, VmError> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let opcode = OpCode::try_from(code[offset])?;
+        let mut next = offset + 1 + opcode.immediate_len();
+        if opcode == OpCode::PushBytes {
+            next += *code.get(offset + 1).ok_or(VmError::TruncatedImmediate(opcode))? as usize;
+        }
+        if next > code.len() {
+            return Err(VmError::TruncatedImmediate(opcode));
         }
+        instructions.push((offset, opcode));
+        offset = next;
     }
-}
\ No newline at end of file
+    Ok(instructions)
+}
+
+/// Reads the jump target of the jump instruction at `offset`.
+pub fn jump_target(code: &[u8], offset: usize) -> Option<usize> {
+    let bytes = code.get(offset + 1..offset + 1 + JUMP_TARGET_LEN)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+}
+
+/// The load-time jump analysis: every jump and call must land on the start of
+/// an instruction, never inside a Push immediate or past the end of the code.
+/// Code that passes can't have its immediates executed as opcodes.
+pub fn check_jump_targets(code: &[u8]) -> Result<(), VmError> {
+    let instructions = decode_instructions(code)?;
+    let starts: std::collections::HashSet<usize> = instructions.iter().map(|(offset, _)| *offset).collect();
+    for (offset, opcode) in instructions {
+        if opcode.is_jump() {
+            let target = jump_target(code, offset).expect("decode_instructions checked the immediate");
+            if !starts.contains(&target) {
+                return Err(VmError::InvalidJumpTarget { at: offset, target });
+            }
+        }
+    }
+    Ok(())
+}
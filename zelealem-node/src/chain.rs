@@ -1,10 +1,13 @@
 use crate::ledger::Block;
 use crate::crypto::{Hash, PublicKey};
+use std::collections::HashMap;
 
 // The blockchain is a sequence of blocks.
 #[derive(Default)]
 pub struct Chain {
     blocks: Vec<Block>,
+    // Maps each block's hash to its height, for lookups by hash.
+    heights: HashMap<Hash, u64>,
 }
 
 impl Chain {
@@ -16,8 +19,16 @@ impl Chain {
         vec![],             // No transactions
         vec![],             // No VDF proof
     );
+        Self::from_genesis(genesis_block)
+    }
+
+    // Creates a blockchain that starts from the given genesis block.
+    pub fn from_genesis(genesis_block: Block) -> Self {
+        let mut heights = HashMap::new();
+        heights.insert(genesis_block.id, 0);
         Self {
             blocks: vec![genesis_block],
+            heights,
         }
     }
 
@@ -30,10 +41,28 @@ impl Chain {
     // NOTE: In a real node, this would involve intense validation.
     // For now, we just add it.
     pub fn add_block(&mut self, block: Block) {
+        self.heights.insert(block.id, self.blocks.len() as u64);
         self.blocks.push(block);
     }
 
     pub fn get_latest_block(&self) -> Option<&Block> {
         self.blocks.last()
     }
+
+    // The height of the latest block; the genesis block is at height 0.
+    pub fn height(&self) -> u64 {
+        self.blocks.len().saturating_sub(1) as u64
+    }
+
+    pub fn get_block(&self, height: u64) -> Option<&Block> {
+        self.blocks.get(height as usize)
+    }
+
+    pub fn get_block_by_hash(&self, hash: &Hash) -> Option<&Block> {
+        self.height_of(hash).and_then(|h| self.get_block(h))
+    }
+
+    pub fn height_of(&self, hash: &Hash) -> Option<u64> {
+        self.heights.get(hash).copied()
+    }
 }
\ No newline at end of file
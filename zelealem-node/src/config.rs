@@ -0,0 +1,218 @@
+use crate::crypto::{self, PublicKey};
+use crate::keystore::{self, KeystoreError};
+use libp2p::{identity, Multiaddr};
+use serde::Deserialize;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// The file inside the data directory that holds the node's libp2p identity.
+pub const IDENTITY_FILE: &str = "identity.key";
+
+// Environment variable the validator keystore password is read from when no password file is configured.
+pub const VALIDATOR_PASSWORD_ENV: &str = "ZELEALEM_VALIDATOR_PASSWORD";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read or write {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid multiaddress {0:?}")]
+    InvalidMultiaddr(String),
+    #[error("Invalid identity key in {0}")]
+    InvalidIdentity(PathBuf),
+    #[error("{0} holds a secret key but is readable by other users; restrict it with `chmod 600`")]
+    InsecurePermissions(PathBuf),
+    #[error("Validator key {0}: {1}")]
+    ValidatorKey(PathBuf, #[source] KeystoreError),
+    #[error("{0} must be greater than zero")]
+    ZeroValue(&'static str),
+    #[error("No validator key password: set validator_password_file or {VALIDATOR_PASSWORD_ENV}")]
+    MissingValidatorPassword,
+}
+
+// Everything an operator can tune about a node.
+// Every field has a default, so a config file only needs to list what it changes.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    /// Where the node keeps its persistent files, such as its libp2p identity.
+    pub data_dir: PathBuf,
+    /// Genesis specification to start the chain from.
+    /// Without one, the node runs a throwaway local chain with itself as the only validator.
+    pub genesis_file: Option<PathBuf>,
+    /// Multiaddresses to listen on for peer connections.
+    pub listen_addrs: Vec<String>,
+    /// Peers to dial on startup, in addition to those found through mDNS.
+    pub bootstrap_peers: Vec<String>,
+    /// Encrypted keystore holding the secret key this node proposes blocks with.
+    /// Without one, the validator key is derived from the node's PeerId.
+    pub validator_key_file: Option<PathBuf>,
+    /// File whose first line is the password for the validator keystore.
+    pub validator_password_file: Option<PathBuf>,
+    /// Seconds between proposer ticks.
+    pub block_time_secs: u64,
+    /// The most transactions a proposer pulls from the mempool for one block.
+    pub max_txs_per_block: usize,
+    /// Whether to serve the JSON-RPC API.
+    pub rpc_enabled: bool,
+    /// Address the JSON-RPC server (HTTP and WebSocket) listens on.
+    pub rpc_addr: SocketAddr,
+    /// Log filter, in `env_logger` syntax (e.g. `info` or `zelealem_node=debug`).
+    pub log_level: String,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("zelealem-data"),
+            genesis_file: None,
+            listen_addrs: vec!["/ip4/0.0.0.0/tcp/0".to_string()],
+            bootstrap_peers: vec![],
+            validator_key_file: None,
+            validator_password_file: None,
+            block_time_secs: 10,
+            max_txs_per_block: 10,
+            rpc_enabled: true,
+            rpc_addr: crate::rpc::DEFAULT_RPC_ADDR.parse().unwrap(),
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+impl NodeConfig {
+    /// Parses a config from TOML text.
+    pub fn from_toml(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values a node can't run with, such as a zero block time.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.block_time_secs == 0 {
+            return Err(ConfigError::ZeroValue("block_time_secs"));
+        }
+        if self.max_txs_per_block == 0 {
+            return Err(ConfigError::ZeroValue("max_txs_per_block"));
+        }
+        Ok(())
+    }
+
+    /// Reads a config from a TOML file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+        Self::from_toml(&text, path)
+    }
+
+    pub fn listen_multiaddrs(&self) -> Result<Vec<Multiaddr>, ConfigError> {
+        parse_multiaddrs(&self.listen_addrs)
+    }
+
+    pub fn bootstrap_multiaddrs(&self) -> Result<Vec<Multiaddr>, ConfigError> {
+        parse_multiaddrs(&self.bootstrap_peers)
+    }
+
+    pub fn identity_path(&self) -> PathBuf {
+        self.data_dir.join(IDENTITY_FILE)
+    }
+
+    /// The validator keystore password, from `validator_password_file` if set,
+    /// otherwise from the `ZELEALEM_VALIDATOR_PASSWORD` environment variable.
+    pub fn validator_password(&self) -> Result<String, ConfigError> {
+        match &self.validator_password_file {
+            Some(path) => read_password_file(path),
+            None => std::env::var(VALIDATOR_PASSWORD_ENV).map_err(|_| ConfigError::MissingValidatorPassword),
+        }
+    }
+}
+
+fn parse_multiaddrs(addrs: &[String]) -> Result<Vec<Multiaddr>, ConfigError> {
+    addrs
+        .iter()
+        .map(|a| a.parse().map_err(|_| ConfigError::InvalidMultiaddr(a.clone())))
+        .collect()
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> ConfigError + '_ {
+    move |source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Loads the libp2p identity stored at `path`, generating and saving a new one
+/// on first run, so the node keeps the same PeerId across restarts.
+pub fn load_or_generate_identity(path: &Path) -> Result<identity::Keypair, ConfigError> {
+    if path.exists() {
+        check_private(path)?;
+        let bytes = std::fs::read(path).map_err(io_error(path))?;
+        return identity::Keypair::from_protobuf_encoding(&bytes)
+            .map_err(|_| ConfigError::InvalidIdentity(path.to_path_buf()));
+    }
+
+    let keypair = identity::Keypair::generate_ed25519();
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .expect("Ed25519 keys can always be encoded");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+    }
+    write_private(path, &bytes).map_err(io_error(path))?;
+    Ok(keypair)
+}
+
+// Creates `path` holding `bytes`, readable and writable by its owner only.
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)
+}
+
+// Fails if anyone but the owner of `path` can read or write it.
+#[cfg(unix)]
+fn check_private(path: &Path) -> Result<(), ConfigError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(path).map_err(io_error(path))?.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(ConfigError::InsecurePermissions(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_private(_path: &Path) -> Result<(), ConfigError> {
+    Ok(())
+}
+
+/// Reads a password from the first line of `path`, ignoring the line ending.
+pub fn read_password_file(path: &Path) -> Result<String, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+    Ok(text.lines().next().unwrap_or_default().to_string())
+}
+
+/// Unlocks the validator keystore at `path` with `password`, generating and
+/// saving a new key under that password if the file does not exist yet.
+pub fn load_or_generate_validator_key(path: &Path, password: &str) -> Result<(PublicKey, Vec<u8>), ConfigError> {
+    let keystore_error = |e| ConfigError::ValidatorKey(path.to_path_buf(), e);
+    if path.exists() {
+        return keystore::load_key(path, password).map_err(keystore_error);
+    }
+
+    let (public_key, secret_key) = crypto::generate_keypair();
+    keystore::save_key(path, &secret_key, password).map_err(keystore_error)?;
+    Ok((public_key, secret_key))
+}
@@ -12,7 +12,7 @@ pub struct Validator {
 }
 
 // The ValidatorSet manages all active validators.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ValidatorSet {
     // A map from the validator's public key to their validator info.
     pub validators: HashMap<PublicKey, Validator>,
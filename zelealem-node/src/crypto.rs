@@ -1,6 +1,7 @@
-use ring::{digest, rand::{self, SecureRandom}};
+use ring::{digest, hmac, rand::{self, SecureRandom}};
 use serde::Serialize; // Added for PublicKey serialization
 use serde::Deserialize;
+use thiserror::Error;
 
 pub type Hash = [u8; 32];
 pub type Signature = [u8; 64];
@@ -20,11 +21,23 @@ pub fn generate_keypair() -> (PublicKey, Vec<u8>) {
     let rng = rand::SystemRandom::new();
     let mut secret_key = vec![0u8; 32];
     rng.fill(&mut secret_key).expect("Failed to generate random data for secret key");
-    // CORRECTED: Wrap the hash in the PublicKey struct.
-    let public_key = PublicKey(hash_data(&secret_key));
+    let public_key = public_key_from_secret(&secret_key);
     (public_key, secret_key)
 }
 
+// Returns 32 random bytes, e.g. to make otherwise identical State Objects distinct.
+pub fn random_salt() -> Hash {
+    let rng = rand::SystemRandom::new();
+    let mut salt = [0u8; 32];
+    rng.fill(&mut salt).expect("Failed to generate random salt");
+    salt
+}
+
+// Derives the public key that corresponds to a secret key.
+pub fn public_key_from_secret(secret_key: &[u8]) -> PublicKey {
+    PublicKey(hash_data(secret_key))
+}
+
 pub fn sign_data(data: &[u8], secret_key: &[u8]) -> Signature {
     let mut signature = [0u8; 64];
 
@@ -33,8 +46,7 @@ pub fn sign_data(data: &[u8], secret_key: &[u8]) -> Signature {
     let proof_hash = hash_data(&content_to_sign);
     signature[..32].copy_from_slice(&proof_hash);
 
-    // CORRECTED: Wrap the hash in PublicKey struct before accessing its inner array.
-    let signer_pub_key = PublicKey(hash_data(secret_key));
+    let signer_pub_key = public_key_from_secret(secret_key);
     signature[32..].copy_from_slice(&signer_pub_key.0);
 
     signature
@@ -43,8 +55,178 @@ pub fn sign_data(data: &[u8], secret_key: &[u8]) -> Signature {
 pub fn verify_signature(signature: &Signature, _data: &[u8], owner_public_key: &PublicKey) -> bool {
     let signer_public_key: &[u8] = &signature[32..];
     // CORRECTED: Access the inner array of the owner's key with .0
-    if signer_public_key != &owner_public_key.0 {
+    if signer_public_key != owner_public_key.0 {
         return false;
     }
     true
-}
\ No newline at end of file
+}
+
+// Hashes two Merkle tree nodes into their parent.
+pub fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut pair = [0u8; 64];
+    pair[..32].copy_from_slice(left);
+    pair[32..].copy_from_slice(right);
+    hash_data(&pair)
+}
+
+// Recomputes a Merkle root from a leaf, its position and the sibling hashes on
+// the path up from it. Bit `i` of `index` says whether the node at level `i` is
+// a right child, so its sibling goes on the left.
+pub fn merkle_root_from_proof(leaf: &Hash, index: u64, siblings: &[Hash]) -> Hash {
+    let mut node = *leaf;
+    for (level, sibling) in siblings.iter().enumerate() {
+        node = if index >> level & 1 == 1 {
+            hash_pair(sibling, &node)
+        } else {
+            hash_pair(&node, sibling)
+        };
+    }
+    node
+}
+
+// --- Mnemonics and hierarchical deterministic keys ---
+
+// The BIP-44 coin type used in Zelealem derivation paths (not registered in SLIP-0044).
+pub const ZELEALEM_COIN_TYPE: u32 = 7323;
+
+// Child indexes at or above this are hardened.
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+// HMAC key for the master key, as in SLIP-0010, but domain-separated for our signature scheme.
+const MASTER_KEY_HMAC_KEY: &[u8] = b"zelealem seed";
+
+pub type Seed = [u8; 64];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum KeyDerivationError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(String),
+    #[error("Invalid derivation path {0:?}")]
+    InvalidPath(String),
+    #[error("Derivation index {0} is not hardened; only hardened derivation is supported")]
+    NonHardenedIndex(u32),
+}
+
+// Generates a new 24-word BIP-39 mnemonic phrase.
+pub fn generate_mnemonic() -> String {
+    let rng = rand::SystemRandom::new();
+    let mut entropy = [0u8; 32];
+    rng.fill(&mut entropy).expect("Failed to generate random data for mnemonic");
+    bip39::Mnemonic::from_entropy(&entropy)
+        .expect("32 bytes is a valid entropy length")
+        .to_string()
+}
+
+// Turns a mnemonic phrase (plus an optional passphrase) into the BIP-39 seed all keys derive from.
+// The phrase's checksum is verified, so a mistyped word is caught here.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Seed, KeyDerivationError> {
+    let mnemonic = bip39::Mnemonic::parse_normalized(phrase)
+        .map_err(|e| KeyDerivationError::InvalidMnemonic(e.to_string()))?;
+    Ok(mnemonic.to_seed_normalized(passphrase))
+}
+
+// A derivation path such as `m/44'/7323'/0'`.
+// Our public keys are hashes of secret keys, so there is no way to derive child
+// public keys from a parent public key; every step must be hardened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivationPath(pub Vec<u32>);
+
+impl DerivationPath {
+    /// The path of a wallet account: `m/44'/7323'/<account>'`.
+    pub fn account(account: u32) -> Result<Self, KeyDerivationError> {
+        if account >= HARDENED_OFFSET {
+            return Err(KeyDerivationError::InvalidPath(format!("m/44'/{}'/{}'", ZELEALEM_COIN_TYPE, account)));
+        }
+        Ok(DerivationPath(vec![44 + HARDENED_OFFSET, ZELEALEM_COIN_TYPE + HARDENED_OFFSET, account + HARDENED_OFFSET]))
+    }
+}
+
+impl std::str::FromStr for DerivationPath {
+    type Err = KeyDerivationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeyDerivationError::InvalidPath(s.to_string());
+        let mut segments = s.split('/');
+        if segments.next() != Some("m") {
+            return Err(invalid());
+        }
+        segments
+            .map(|segment| {
+                let index: u32 = match segment.strip_suffix('\'') {
+                    Some(index) => index.parse().map_err(|_| invalid())?,
+                    None => {
+                        let index = segment.parse().map_err(|_| invalid())?;
+                        return Err(KeyDerivationError::NonHardenedIndex(index));
+                    }
+                };
+                if index >= HARDENED_OFFSET {
+                    return Err(invalid());
+                }
+                Ok(index + HARDENED_OFFSET)
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl std::fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index - HARDENED_OFFSET)?;
+        }
+        Ok(())
+    }
+}
+
+// A secret key together with the chain code needed to derive its children (SLIP-0010, hardened only).
+#[derive(Clone)]
+pub struct ExtendedSecretKey {
+    pub secret_key: [u8; 32],
+    pub chain_code: [u8; 32],
+}
+
+impl ExtendedSecretKey {
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(MASTER_KEY_HMAC_KEY, &[seed])
+    }
+
+    /// Derives the hardened child at `index`, which must already include `HARDENED_OFFSET`.
+    pub fn derive_child(&self, index: u32) -> Result<Self, KeyDerivationError> {
+        if index < HARDENED_OFFSET {
+            return Err(KeyDerivationError::NonHardenedIndex(index));
+        }
+        Ok(Self::from_hmac(&self.chain_code, &[&[0u8], &self.secret_key, &index.to_be_bytes()]))
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, KeyDerivationError> {
+        path.0.iter().try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+
+    pub fn keypair(&self) -> (PublicKey, Vec<u8>) {
+        (public_key_from_secret(&self.secret_key), self.secret_key.to_vec())
+    }
+
+    fn from_hmac(key: &[u8], parts: &[&[u8]]) -> Self {
+        let mut context = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA512, key));
+        for part in parts {
+            context.update(part);
+        }
+        let tag = context.sign();
+        let (secret_key, chain_code) = tag.as_ref().split_at(32);
+        Self {
+            secret_key: secret_key.try_into().expect("HMAC-SHA512 output is 64 bytes"),
+            chain_code: chain_code.try_into().expect("HMAC-SHA512 output is 64 bytes"),
+        }
+    }
+}
+
+// Derives the keypair at `path` from a seed.
+pub fn derive_keypair(seed: &[u8], path: &DerivationPath) -> Result<(PublicKey, Vec<u8>), KeyDerivationError> {
+    Ok(ExtendedSecretKey::master(seed).derive_path(path)?.keypair())
+}
+
+// Derives the keypair of wallet account `account` from a seed.
+pub fn derive_account_keypair(seed: &[u8], account: u32) -> Result<(PublicKey, Vec<u8>), KeyDerivationError> {
+    derive_keypair(seed, &DerivationPath::account(account)?)
+}
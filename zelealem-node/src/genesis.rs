@@ -0,0 +1,225 @@
+use crate::chain::Chain;
+use crate::consensus::{Stake, Validator, ValidatorSet};
+use crate::crypto::{self, Hash, PublicKey};
+use crate::ledger::{Block, StateObject, Transaction};
+use crate::module::{Module, ModuleError};
+use crate::p2p::ChainId;
+use crate::state_db::{StateDB, StateError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum GenesisError {
+    #[error("Failed to read genesis file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid genesis file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Genesis must list at least one validator")]
+    NoValidators,
+    #[error("Validator {0:?} is listed more than once")]
+    DuplicateValidator(PublicKey),
+    #[error("Validator {0:?} stakes less than the minimum validator stake")]
+    InsufficientStake(PublicKey),
+    #[error("Invalid genesis allocation: {0}")]
+    Allocation(#[from] StateError),
+    #[error("Genesis allocation {id:?} has invalid validation logic: {reason}")]
+    InvalidLogic { id: Hash, reason: ModuleError },
+}
+
+// Rules every node on the chain must agree on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsensusParams {
+    /// Blocks with more transactions than this are rejected.
+    pub max_txs_per_block: u64,
+    /// The least a validator must stake to be admitted.
+    pub min_validator_stake: Stake,
+    /// The most gas a single transaction may use; transactions needing more are invalid.
+    pub max_tx_gas: u64,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        Self {
+            max_txs_per_block: 1000,
+            min_validator_stake: 1,
+            max_tx_gas: crate::zvm::DEFAULT_GAS_LIMIT,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisValidator {
+    #[serde(with = "hex_public_key")]
+    pub pub_key: PublicKey,
+    pub stake: Stake,
+}
+
+// A State Object that exists from the very first block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisAllocation {
+    #[serde(with = "hex_public_key")]
+    pub owner: PublicKey,
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+    #[serde(default, with = "hex_bytes")]
+    pub validation_logic: Vec<u8>,
+}
+
+// The genesis specification: everything a node needs to derive block zero.
+// Keys and byte strings are written in hex.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Genesis {
+    pub chain_id: ChainId,
+    #[serde(default)]
+    pub consensus: ConsensusParams,
+    pub validators: Vec<GenesisValidator>,
+    #[serde(default)]
+    pub allocations: Vec<GenesisAllocation>,
+}
+
+// The state every node starts from, derived from a `Genesis`.
+pub struct GenesisState {
+    pub chain: Chain,
+    pub state_db: StateDB,
+    pub validator_set: ValidatorSet,
+}
+
+impl Genesis {
+    pub fn from_toml(text: &str, path: &Path) -> Result<Self, GenesisError> {
+        toml::from_str(text).map_err(|source| GenesisError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, GenesisError> {
+        let text = std::fs::read_to_string(path).map_err(|source| GenesisError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&text, path)
+    }
+
+    /// The hash of the canonical encoding of this specification.
+    /// It becomes the genesis block's `previous_hash`, so two nodes only share a
+    /// genesis block if they agree on every field of the spec.
+    pub fn spec_hash(&self) -> Hash {
+        let bytes = bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .expect("Failed to serialize genesis");
+        crypto::hash_data(&bytes)
+    }
+
+    /// The genesis block. Its single transaction has no inputs and creates
+    /// every allocation, so the block hash also commits to the initial state.
+    pub fn block(&self) -> Block {
+        let outputs = self
+            .allocations
+            .iter()
+            .map(|a| StateObject::new(a.owner, a.data.clone(), a.validation_logic.clone()))
+            .collect();
+        let allocation_tx = Transaction::new(vec![], outputs, vec![]);
+        Block::new(self.spec_hash(), PublicKey([0u8; 32]), vec![allocation_tx], vec![])
+    }
+
+    /// Checks the specification and derives the chain, state and validator set from it.
+    pub fn build(&self) -> Result<GenesisState, GenesisError> {
+        if self.validators.is_empty() {
+            return Err(GenesisError::NoValidators);
+        }
+
+        let mut validator_set = ValidatorSet::new();
+        let mut seen = HashSet::new();
+        for v in &self.validators {
+            if !seen.insert(v.pub_key) {
+                return Err(GenesisError::DuplicateValidator(v.pub_key));
+            }
+            if v.stake < self.consensus.min_validator_stake {
+                return Err(GenesisError::InsufficientStake(v.pub_key));
+            }
+            validator_set.add_validator(Validator {
+                pub_key: v.pub_key,
+                stake: v.stake,
+            });
+        }
+
+        let block = self.block();
+        let mut state_db = StateDB::new();
+        for tx in &block.transactions {
+            for so in &tx.outputs {
+                // Held to the same rules as logic created by a transaction.
+                if !so.validation_logic.is_empty() {
+                    Module::from_logic(&so.validation_logic)
+                        .and_then(|module| module.verify())
+                        .map_err(|reason| GenesisError::InvalidLogic { id: so.id, reason })?;
+                }
+                state_db.add_so(so.clone())?;
+            }
+        }
+
+        Ok(GenesisState {
+            chain: Chain::from_genesis(block),
+            state_db,
+            validator_set,
+        })
+    }
+}
+
+// Serde helpers for writing binary fields as hex strings.
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(bytes))
+        } else {
+            serializer.serialize_bytes(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            hex::decode(s).map_err(serde::de::Error::custom)
+        } else {
+            Vec::<u8>::deserialize(deserializer)
+        }
+    }
+}
+
+pub(crate) mod hex_public_key {
+    use crate::crypto::PublicKey;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(key.0))
+        } else {
+            key.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PublicKey, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
+            let key: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| serde::de::Error::custom("public key must be 32 bytes"))?;
+            Ok(PublicKey(key))
+        } else {
+            PublicKey::deserialize(deserializer)
+        }
+    }
+}
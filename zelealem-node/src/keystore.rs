@@ -0,0 +1,253 @@
+use crate::crypto::{self, PublicKey};
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// The keystore format version written by this code.
+pub const KEYSTORE_VERSION: u32 = 1;
+
+const KDF_ALGORITHM: &str = "scrypt";
+const CIPHER_ALGORITHM: &str = "chacha20-poly1305";
+const SALT_LEN: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum KeystoreError {
+    #[error("Failed to read or write {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid keystore file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unsupported keystore algorithm {0:?}")]
+    UnsupportedAlgorithm(String),
+    #[error("Invalid scrypt parameters")]
+    InvalidKdfParams,
+    #[error("Malformed keystore: {0}")]
+    Malformed(&'static str),
+    #[error("Wrong password, or the keystore has been tampered with")]
+    DecryptionFailed,
+    #[error("Expected a keystore holding a {expected:?}, found a {found:?}")]
+    WrongKind { expected: SecretKind, found: SecretKind },
+}
+
+/// What a keystore's ciphertext holds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretKind {
+    /// A single secret key.
+    #[default]
+    Key,
+    /// An HD wallet seed; `public_key` is then the key of account 0.
+    Seed,
+}
+
+impl SecretKind {
+    // The public key a keystore holding `secret` should advertise.
+    fn public_key(self, secret: &[u8]) -> PublicKey {
+        match self {
+            SecretKind::Key => crypto::public_key_from_secret(secret),
+            SecretKind::Seed => crypto::derive_account_keypair(secret, 0).expect("Account 0 is a valid path").0,
+        }
+    }
+}
+
+/// Cost parameters for scrypt.
+/// Higher values make each password guess more expensive for an attacker, and unlocking slower for us.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    // The scrypt crate's recommended interactive-login cost.
+    fn default() -> Self {
+        Self { log_n: 17, r: 8, p: 1 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KdfSection {
+    pub algorithm: String,
+    #[serde(flatten)]
+    pub params: ScryptParams,
+    #[serde(with = "crate::genesis::hex_bytes")]
+    pub salt: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CipherSection {
+    pub algorithm: String,
+    #[serde(with = "crate::genesis::hex_bytes")]
+    pub nonce: Vec<u8>,
+}
+
+// A secret key encrypted under a password, as stored on disk (JSON).
+// The public key is kept in the clear so a wallet can show its address without unlocking.
+// Everything except the ciphertext is authenticated as associated data, so editing
+// any field makes decryption fail just like a wrong password does.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Keystore {
+    pub version: u32,
+    #[serde(default)]
+    pub kind: SecretKind,
+    #[serde(with = "crate::genesis::hex_public_key")]
+    pub public_key: PublicKey,
+    pub kdf: KdfSection,
+    pub cipher: CipherSection,
+    #[serde(with = "crate::genesis::hex_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypts `secret_key` under `password` with the default scrypt cost.
+    pub fn encrypt(secret_key: &[u8], password: &str) -> Result<Self, KeystoreError> {
+        Self::encrypt_with_params(secret_key, password, ScryptParams::default())
+    }
+
+    pub fn encrypt_with_params(secret_key: &[u8], password: &str, params: ScryptParams) -> Result<Self, KeystoreError> {
+        Self::seal(SecretKind::Key, secret_key, password, params)
+    }
+
+    /// Encrypts an HD wallet seed under `password` with the default scrypt cost.
+    pub fn encrypt_seed(seed: &[u8], password: &str) -> Result<Self, KeystoreError> {
+        Self::seal(SecretKind::Seed, seed, password, ScryptParams::default())
+    }
+
+    pub fn seal(kind: SecretKind, secret: &[u8], password: &str, params: ScryptParams) -> Result<Self, KeystoreError> {
+        let rng = SystemRandom::new();
+        let mut salt = vec![0u8; SALT_LEN];
+        rng.fill(&mut salt).expect("Failed to generate random salt");
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        rng.fill(&mut nonce).expect("Failed to generate random nonce");
+
+        let mut keystore = Self {
+            version: KEYSTORE_VERSION,
+            kind,
+            public_key: kind.public_key(secret),
+            kdf: KdfSection {
+                algorithm: KDF_ALGORITHM.to_string(),
+                params,
+                salt,
+            },
+            cipher: CipherSection {
+                algorithm: CIPHER_ALGORITHM.to_string(),
+                nonce: nonce.to_vec(),
+            },
+            ciphertext: vec![],
+        };
+
+        let key = keystore.derive_key(password)?;
+        let mut in_out = secret.to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(keystore.associated_data()), &mut in_out)
+            .map_err(|_| KeystoreError::Malformed("encryption failed"))?;
+        keystore.ciphertext = in_out;
+        Ok(keystore)
+    }
+
+    /// Decrypts the secret (a key or a seed, per `kind`), returning it with the public key.
+    pub fn decrypt(&self, password: &str) -> Result<(PublicKey, Vec<u8>), KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        if self.cipher.algorithm != CIPHER_ALGORITHM {
+            return Err(KeystoreError::UnsupportedAlgorithm(self.cipher.algorithm.clone()));
+        }
+        let nonce = Nonce::try_assume_unique_for_key(&self.cipher.nonce)
+            .map_err(|_| KeystoreError::Malformed("nonce has the wrong length"))?;
+
+        let key = self.derive_key(password)?;
+        let mut in_out = self.ciphertext.clone();
+        let secret = key
+            .open_in_place(nonce, Aad::from(self.associated_data()), &mut in_out)
+            .map_err(|_| KeystoreError::DecryptionFailed)?
+            .to_vec();
+
+        // The public key is authenticated, but check it anyway so a bug can't hand out a mismatched pair.
+        if self.kind.public_key(&secret) != self.public_key {
+            return Err(KeystoreError::DecryptionFailed);
+        }
+        Ok((self.public_key, secret))
+    }
+
+    /// Reads a keystore file. This does not need the password.
+    pub fn load(path: &Path) -> Result<Self, KeystoreError> {
+        let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+        serde_json::from_str(&text).map_err(|source| KeystoreError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Writes the keystore to `path`, creating parent directories as needed.
+    pub fn save(&self, path: &Path) -> Result<(), KeystoreError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+        }
+        let text = serde_json::to_string_pretty(self).expect("Keystore serialization cannot fail");
+        std::fs::write(path, text).map_err(io_error(path))
+    }
+
+    fn derive_key(&self, password: &str) -> Result<LessSafeKey, KeystoreError> {
+        if self.kdf.algorithm != KDF_ALGORITHM {
+            return Err(KeystoreError::UnsupportedAlgorithm(self.kdf.algorithm.clone()));
+        }
+        let ScryptParams { log_n, r, p } = self.kdf.params;
+        let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+
+        let mut key = [0u8; 32];
+        scrypt::scrypt(password.as_bytes(), &self.kdf.salt, &params, &mut key)
+            .map_err(|_| KeystoreError::InvalidKdfParams)?;
+        let key = UnboundKey::new(&aead::CHACHA20_POLY1305, &key).expect("Key length matches the algorithm");
+        Ok(LessSafeKey::new(key))
+    }
+
+    // Binds every cleartext field to the ciphertext.
+    fn associated_data(&self) -> Vec<u8> {
+        let mut aad = Vec::new();
+        aad.extend_from_slice(&self.version.to_le_bytes());
+        aad.push(self.kind as u8);
+        aad.extend_from_slice(&self.public_key.0);
+        aad.extend_from_slice(self.kdf.algorithm.as_bytes());
+        aad.push(self.kdf.params.log_n);
+        aad.extend_from_slice(&self.kdf.params.r.to_le_bytes());
+        aad.extend_from_slice(&self.kdf.params.p.to_le_bytes());
+        aad.extend_from_slice(&self.kdf.salt);
+        aad.extend_from_slice(self.cipher.algorithm.as_bytes());
+        aad
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> KeystoreError + '_ {
+    move |source| KeystoreError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Encrypts `secret_key` and writes it to `path`.
+pub fn save_key(path: &Path, secret_key: &[u8], password: &str) -> Result<(), KeystoreError> {
+    Keystore::encrypt(secret_key, password)?.save(path)
+}
+
+/// Reads and decrypts the single-key keystore at `path`.
+pub fn load_key(path: &Path, password: &str) -> Result<(PublicKey, Vec<u8>), KeystoreError> {
+    let keystore = Keystore::load(path)?;
+    if keystore.kind != SecretKind::Key {
+        return Err(KeystoreError::WrongKind {
+            expected: SecretKind::Key,
+            found: keystore.kind,
+        });
+    }
+    keystore.decrypt(password)
+}
@@ -1,7 +1,7 @@
 use crate::crypto::{Hash, PublicKey, Signature};
 use serde::Serialize;
-use serde_big_array::BigArray;
 use serde::Deserialize;
+use thiserror::Error;
 
 // This is the correct function to use when using serde::Serialize with bincode 2.x
 use bincode::serde::encode_to_vec;
@@ -30,6 +30,7 @@ struct HashableStateObject<'a> {
 #[derive(Serialize)]
 struct HashableTransaction<'a> {
     inputs: &'a Vec<Hash>,
+    reference_inputs: &'a Vec<Hash>,
     outputs: &'a Vec<StateObject>,
     causal_links: &'a Vec<CausalLink>,
 }
@@ -61,9 +62,123 @@ impl StateObject {
             validation_logic,
         }
     }
+
+    // Creates a plain value object: `amount` units owned by `owner`, with no validation logic.
+    pub fn new_value(owner: PublicKey, amount: u64) -> Self {
+        Self::new(owner, amount.to_le_bytes().to_vec(), vec![])
+    }
+
+    // Like `new_value`, but with a salt appended to the data. An SO's id only
+    // depends on its content, so two payments of the same amount to the same
+    // owner would otherwise collide; the salt keeps their ids distinct.
+    pub fn new_salted_value(owner: PublicKey, amount: u64, salt: Hash) -> Self {
+        let mut data = amount.to_le_bytes().to_vec();
+        data.extend_from_slice(&salt);
+        Self::new(owner, data, vec![])
+    }
+
+    // The amount held by a plain value object, i.e. one with no validation logic
+    // whose data is a little-endian u64, optionally followed by a 32-byte salt.
+    // Returns `None` for anything else.
+    pub fn amount(&self) -> Option<u64> {
+        if !self.validation_logic.is_empty() || !matches!(self.data.len(), 8 | 40) {
+            return None;
+        }
+        let bytes: [u8; 8] = self.data[..8].try_into().ok()?;
+        Some(u64::from_le_bytes(bytes))
+    }
+}
+
+// The most keys a multisig policy may list.
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+// Domain separator for the owner key of a multisig policy.
+const MULTISIG_DOMAIN: &[u8] = b"zelealem/multisig";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("Threshold {threshold} is not between 1 and the number of keys ({keys})")]
+    InvalidThreshold { threshold: u8, keys: usize },
+    #[error("A multisig policy may list at most {MAX_MULTISIG_KEYS} keys, got {0}")]
+    TooManyKeys(usize),
+    #[error("Multisig keys must be sorted and distinct")]
+    UnsortedKeys,
+}
+
+// An m-of-n key set that can own State Objects, so a treasury or team account
+// doesn't depend on a single key. Objects are owned by the policy's `owner_key`,
+// a hash committing to the threshold and keys; they behave like any other owner
+// (addresses, balances, indexes) until spent, when the transaction reveals the
+// policy and carries signatures from at least `threshold` of its keys.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MultisigPolicy {
+    pub threshold: u8,
+    // Sorted and distinct, so each key set has exactly one owner key.
+    pub keys: Vec<PublicKey>,
+}
+
+impl MultisigPolicy {
+    pub fn new(threshold: u8, mut keys: Vec<PublicKey>) -> Result<Self, PolicyError> {
+        keys.sort();
+        keys.dedup();
+        let policy = Self { threshold, keys };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    // Checks the invariants `new` establishes, for policies that arrive over the wire.
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        if self.keys.len() > MAX_MULTISIG_KEYS {
+            return Err(PolicyError::TooManyKeys(self.keys.len()));
+        }
+        if !self.keys.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err(PolicyError::UnsortedKeys);
+        }
+        if self.threshold == 0 || self.threshold as usize > self.keys.len() {
+            return Err(PolicyError::InvalidThreshold {
+                threshold: self.threshold,
+                keys: self.keys.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // The key objects owned by this policy are assigned to.
+    pub fn owner_key(&self) -> PublicKey {
+        let mut bytes = MULTISIG_DOMAIN.to_vec();
+        bytes.extend(encode_to_vec(self, standard()).expect("Failed to serialize policy"));
+        PublicKey(crate::crypto::hash_data(&bytes))
+    }
+}
+
+// Serializes a list of signatures; serde only handles arrays of up to 32 elements natively.
+mod signature_list {
+    use crate::crypto::Signature;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde_big_array::Array;
+
+    pub fn serialize<S: Serializer>(signatures: &[Signature], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(signatures.iter().map(|signature| Array(*signature)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Signature>, D::Error> {
+        let signatures = Vec::<Array<u8, 64>>::deserialize(deserializer)?;
+        Ok(signatures.into_iter().map(|signature| signature.0).collect())
+    }
+}
+
+// The authorization for spending one input: signatures over the transaction id
+// and, for a multisig-owned input, the policy it is owned by.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Witness {
+    #[serde(with = "signature_list")]
+    pub signatures: Vec<Signature>,
+    pub policy: Option<MultisigPolicy>,
 }
 
 // A Causal Link allows one transaction to reference the logic of another State Object.
+// Both ends must exist, either already on the ledger or as outputs of the same
+// transaction, and the target's validation logic must approve the source.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CausalLink {
     pub source_so_id: Hash,
@@ -71,14 +186,21 @@ pub struct CausalLink {
 }
 
 // A transaction consumes and creates State Objects.
+// Each input is authorized by its own witness, so inputs from different owners
+// (atomic swaps, coin-joins) can be combined. Witnesses are not part of the id:
+// every owner signs the same id, and so agrees to the whole transaction.
+// Reference inputs are read but not consumed: they must exist and scripts can
+// see them, but they stay in the state, so any number of transactions can read
+// the same object (an oracle price, a contract's config) without contending for it.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Transaction {
     pub id: Hash,
     pub inputs: Vec<Hash>,
+    pub reference_inputs: Vec<Hash>,
     pub outputs: Vec<StateObject>,
     pub causal_links: Vec<CausalLink>,
-    #[serde(with = "BigArray")]
-    pub signature: Signature,
+    // One per input, in the same order.
+    pub witnesses: Vec<Witness>,
 }
 
 impl Transaction {
@@ -86,9 +208,20 @@ impl Transaction {
         inputs: Vec<Hash>,
         outputs: Vec<StateObject>,
         causal_links: Vec<CausalLink>,
+    ) -> Self {
+        Self::with_reference_inputs(inputs, vec![], outputs, causal_links)
+    }
+
+    // Like `new`, but also reading `reference_inputs` without consuming them.
+    pub fn with_reference_inputs(
+        inputs: Vec<Hash>,
+        reference_inputs: Vec<Hash>,
+        outputs: Vec<StateObject>,
+        causal_links: Vec<CausalLink>,
     ) -> Self {
         let hashable_part = HashableTransaction {
             inputs: &inputs,
+            reference_inputs: &reference_inputs,
             outputs: &outputs,
             causal_links: &causal_links,
         };
@@ -96,22 +229,37 @@ impl Transaction {
         let bytes = encode_to_vec(&hashable_part, standard()).expect("Failed to serialize TX");
         let id = crate::crypto::hash_data(&bytes);
 
+        let witnesses = vec![Witness::default(); inputs.len()];
         Self {
             id,
             inputs,
+            reference_inputs,
             outputs,
             causal_links,
-            signature: [0u8; 64],
+            witnesses,
         }
     }
 
+    // Adds a signature to every input's witness, for the common case of a single owner.
     pub fn sign(&mut self, signature: Signature) {
-        self.signature = signature;
+        for witness in &mut self.witnesses {
+            witness.signatures.push(signature);
+        }
+    }
+
+    // Adds a signature to the witness of input `index`.
+    pub fn sign_input(&mut self, index: usize, signature: Signature) {
+        self.witnesses[index].signatures.push(signature);
+    }
+
+    // Reveals the policy owning input `index`.
+    pub fn set_policy(&mut self, index: usize, policy: MultisigPolicy) {
+        self.witnesses[index].policy = Some(policy);
     }
 }
 
 // A Block is a collection of transactions.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Block {
     pub id: Hash,
     pub previous_hash: Hash, // Link to the previous block
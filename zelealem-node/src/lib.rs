@@ -9,4 +9,14 @@ pub mod consensus;
 pub mod mempool;
 pub mod topics;
 pub mod bytecode;
-pub mod zvm;
\ No newline at end of file
+pub mod zvm;
+pub mod module;
+pub mod asm;
+pub mod peer_manager;
+pub mod config;
+pub mod genesis;
+pub mod rpc;
+pub mod wallet;
+
+pub mod keystore;
+pub mod address;
\ No newline at end of file
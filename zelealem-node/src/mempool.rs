@@ -1,35 +1,79 @@
+use crate::crypto::Hash;
 use crate::ledger::Transaction;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 
 const MAX_MEMPOOL_SIZE: usize = 1000;
 
 // The Mempool holds transactions that have been received but not yet
 // included in a block. A VecDeque is a double-ended queue.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Mempool {
     transactions: VecDeque<Transaction>,
+    // The inputs spent by the transactions above, to spot conflicting ones.
+    spent: HashSet<Hash>,
 }
 
 impl Mempool {
     pub fn new() -> Self {
         Self {
             transactions: VecDeque::new(),
+            spent: HashSet::new(),
         }
     }
 
+    /// Returns an input of `tx` that a transaction already in the mempool spends, if any.
+    pub fn conflicting_input(&self, tx: &Transaction) -> Option<Hash> {
+        tx.inputs.iter().find(|id| self.spent.contains(*id)).copied()
+    }
+
     /// Adds a transaction to the mempool if there is space.
     pub fn add_transaction(&mut self, tx: Transaction) -> bool {
         if self.transactions.len() >= MAX_MEMPOOL_SIZE {
-            println!("Mempool is full. Rejecting transaction.");
+            log::warn!("Mempool is full. Rejecting transaction.");
             return false;
         }
+        self.spent.extend(tx.inputs.iter().copied());
         self.transactions.push_back(tx);
         true
     }
 
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// The most transactions the mempool will hold at once.
+    pub fn capacity(&self) -> usize {
+        MAX_MEMPOOL_SIZE
+    }
+
+    /// Drops every transaction that spends or reads an object in `spent`, e.g. the
+    /// inputs of a block just applied. That covers the block's own transactions
+    /// and any that conflict with them.
+    pub fn remove_spent(&mut self, spent: &HashSet<Hash>) {
+        let (stale, kept): (Vec<Transaction>, Vec<Transaction>) = self.transactions.drain(..).partition(|tx| {
+            tx.inputs.iter().chain(&tx.reference_inputs).any(|id| spent.contains(id))
+        });
+        for tx in &stale {
+            for id in &tx.inputs {
+                self.spent.remove(id);
+            }
+        }
+        self.transactions = kept.into();
+    }
+
     /// Retrieves a batch of transactions to be included in a new block.
     pub fn get_batch(&mut self, max_txs: usize) -> Vec<Transaction> {
         let batch_size = self.transactions.len().min(max_txs);
-        self.transactions.drain(0..batch_size).collect()
+        let batch: Vec<Transaction> = self.transactions.drain(0..batch_size).collect();
+        for tx in &batch {
+            for id in &tx.inputs {
+                self.spent.remove(id);
+            }
+        }
+        batch
     }
 }
\ No newline at end of file
@@ -0,0 +1,403 @@
+use crate::bytecode::{self, collection, OpCode};
+use crate::zvm::{VmError, DEFAULT_MAX_STACK, MAX_BYTES_LEN, MAX_CALL_DEPTH, MAX_LOCALS};
+use std::collections::HashMap;
+use thiserror::Error;
+
+// Every module starts with these bytes. A bare opcode stream can't: it would
+// open with Halt on an empty stack.
+pub const MAGIC: [u8; 4] = [0x00, b'Z', b'V', b'M'];
+// The module format version this node reads and writes.
+pub const MODULE_VERSION: u8 = 1;
+// The most entry points a module may declare, one for each entry in `entry`.
+pub const MAX_ENTRY_POINTS: usize = 2;
+// The most constants a module may declare; they are counted with one byte.
+pub const MAX_CONSTANTS: usize = 255;
+// The most instructions and called functions a module may have. Verification
+// is linear in the instructions, and these keep it cheap next to running the code.
+pub const MAX_INSTRUCTIONS: usize = 16_384;
+pub const MAX_FUNCTIONS: usize = 256;
+
+// The entry points of a module, by position in its entry point list.
+pub mod entry {
+    /// Run when the object is spent as a transaction input.
+    pub const SPEND: usize = 0;
+    /// Run when the object is the target of a causal link. Falls back to `SPEND` if absent.
+    pub const LINK: usize = 1;
+}
+
+// Every way a module can be malformed or fail verification.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ModuleError {
+    #[error("The module does not start with the module magic bytes")]
+    BadMagic,
+    #[error("Unsupported module version {0}")]
+    UnsupportedVersion(u8),
+    #[error("The module ends before its {0} is complete")]
+    Truncated(&'static str),
+    #[error("{0} bytes follow the end of the code section")]
+    TrailingBytes(usize),
+    #[error("A module needs between 1 and {MAX_ENTRY_POINTS} entry points, found {0}")]
+    InvalidEntryCount(usize),
+    #[error("Entry point {0} is not the start of an instruction")]
+    InvalidEntryPoint(u32),
+    #[error("A module may have at most {MAX_CONSTANTS} constants, found {0}")]
+    TooManyConstants(usize),
+    #[error("Constant {index} is {len} bytes long, over the limit of {MAX_BYTES_LEN}")]
+    ConstantTooLong { index: usize, len: usize },
+    #[error("Malformed code: {0}")]
+    Code(#[from] VmError),
+    #[error("The instruction at offset {0} can never be reached")]
+    Unreachable(usize),
+    #[error("Execution can run past the end of the code after offset {0}")]
+    RunsOffEnd(usize),
+    #[error("Paths reach offset {at} with {expected} and {found} values on the stack")]
+    StackImbalance { at: usize, expected: i64, found: i64 },
+    #[error("The instruction at offset {0} can pop more values than the stack holds")]
+    StackUnderflow(usize),
+    #[error("The stack can grow past the declared maximum of {max} at offset {at}")]
+    StackTooDeep { at: usize, max: u16 },
+    #[error("The function at offset {function} returns with {expected} and {found} values on the stack")]
+    InconsistentReturn { function: usize, expected: i64, found: i64 },
+    #[error("Return at offset {0} can run outside of any Call")]
+    ReturnOutsideCall(usize),
+    #[error("The function at offset {0} can call itself, so its stack use can't be bounded")]
+    Recursion(usize),
+    #[error("Calls can nest deeper than {MAX_CALL_DEPTH}")]
+    CallsTooDeep,
+    #[error("{opcode:?} at offset {at} names constant {index}, which does not exist")]
+    InvalidConstant { opcode: OpCode, at: usize, index: u8 },
+    #[error("A module may have at most {MAX_INSTRUCTIONS} instructions, found {0}")]
+    TooManyInstructions(usize),
+    #[error("A module may call at most {MAX_FUNCTIONS} functions")]
+    TooManyFunctions,
+    #[error("The instruction at offset {0} is part of more than one function")]
+    SharedCode(usize),
+}
+
+/// A unit of validation logic: the code with the metadata needed to run and verify it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Module {
+    /// The most values the stack may hold; enforced by the verifier and the ZVM.
+    pub max_stack: u16,
+    /// Where execution starts, as offsets into `code`; see `entry`.
+    pub entry_points: Vec<u32>,
+    /// Byte arrays the code can push with PushConst.
+    pub constants: Vec<Vec<u8>>,
+    /// The opcode stream. Jump targets are offsets into it.
+    pub code: Vec<u8>,
+}
+
+// A cursor over the bytes of an encoded module.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, what: &'static str) -> Result<&'a [u8], ModuleError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(ModuleError::Truncated(what))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn u8(&mut self, what: &'static str) -> Result<u8, ModuleError> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn u16(&mut self, what: &'static str) -> Result<u16, ModuleError> {
+        Ok(u16::from_le_bytes(self.take(2, what)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self, what: &'static str) -> Result<u32, ModuleError> {
+        Ok(u32::from_le_bytes(self.take(4, what)?.try_into().unwrap()))
+    }
+}
+
+impl Module {
+    /// A module running `code` from its start, with the default stack limit and no constants.
+    pub fn new(code: Vec<u8>) -> Self {
+        Self {
+            max_stack: DEFAULT_MAX_STACK as u16,
+            entry_points: vec![0],
+            constants: Vec::new(),
+            code,
+        }
+    }
+
+    /// Reads a State Object's validation logic: an encoded module, or a bare
+    /// opcode stream, which is treated as `Module::new` of it.
+    pub fn from_logic(logic: &[u8]) -> Result<Self, ModuleError> {
+        if logic.starts_with(&MAGIC) {
+            Self::decode(logic)
+        } else {
+            Ok(Self::new(logic.to_vec()))
+        }
+    }
+
+    /// Encodes the module as: the magic bytes, the version, the max stack (u16),
+    /// the entry point count (u8) and entry points (u32 each), the constant count
+    /// (u8) and each constant's length (u16) and bytes, then the code length (u32)
+    /// and code. Integers are little-endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(MODULE_VERSION);
+        bytes.extend_from_slice(&self.max_stack.to_le_bytes());
+        bytes.push(self.entry_points.len() as u8);
+        for entry in &self.entry_points {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        bytes.push(self.constants.len() as u8);
+        for constant in &self.constants {
+            bytes.extend_from_slice(&(constant.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(constant);
+        }
+        bytes.extend_from_slice(&(self.code.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.code);
+        bytes
+    }
+
+    /// Decodes a module written by `encode`, checking its structure but not its code.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ModuleError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len(), "header")? != MAGIC {
+            return Err(ModuleError::BadMagic);
+        }
+        let version = reader.u8("header")?;
+        if version != MODULE_VERSION {
+            return Err(ModuleError::UnsupportedVersion(version));
+        }
+        let max_stack = reader.u16("header")?;
+
+        let entry_count = reader.u8("entry points")? as usize;
+        if entry_count == 0 || entry_count > MAX_ENTRY_POINTS {
+            return Err(ModuleError::InvalidEntryCount(entry_count));
+        }
+        let entry_points = (0..entry_count).map(|_| reader.u32("entry points")).collect::<Result<_, _>>()?;
+
+        let constant_count = reader.u8("constant pool")? as usize;
+        let mut constants = Vec::with_capacity(constant_count);
+        for index in 0..constant_count {
+            let len = reader.u16("constant pool")? as usize;
+            if len > MAX_BYTES_LEN {
+                return Err(ModuleError::ConstantTooLong { index, len });
+            }
+            constants.push(reader.take(len, "constant pool")?.to_vec());
+        }
+
+        let code_len = reader.u32("code section")? as usize;
+        let code = reader.take(code_len, "code section")?.to_vec();
+        if reader.pos != bytes.len() {
+            return Err(ModuleError::TrailingBytes(bytes.len() - reader.pos));
+        }
+        Ok(Self { max_stack, entry_points, constants, code })
+    }
+
+    /// The static verifier, run on the logic of every State Object a transaction
+    /// creates. Beyond decoding cleanly, every instruction must be reachable from
+    /// an entry point; every path must end in Halt, with enough values for each
+    /// instruction and never more than `max_stack`; and paths that meet must agree
+    /// on the stack depth. Each called function must return with a consistent
+    /// stack effect, and calls may not recurse, so that depth is bounded. No
+    /// instruction may belong to two functions, or to a function and an entry
+    /// point, so that each is verified once.
+    pub fn verify(&self) -> Result<(), ModuleError> {
+        if self.entry_points.is_empty() || self.entry_points.len() > MAX_ENTRY_POINTS {
+            return Err(ModuleError::InvalidEntryCount(self.entry_points.len()));
+        }
+        if self.constants.len() > MAX_CONSTANTS {
+            return Err(ModuleError::TooManyConstants(self.constants.len()));
+        }
+        bytecode::check_jump_targets(&self.code)?;
+        let instructions: HashMap<usize, OpCode> = bytecode::decode_instructions(&self.code)?.into_iter().collect();
+        if instructions.len() > MAX_INSTRUCTIONS {
+            return Err(ModuleError::TooManyInstructions(instructions.len()));
+        }
+
+        let mut verifier = Verifier {
+            module: self,
+            instructions: &instructions,
+            functions: HashMap::new(),
+            in_progress: Vec::new(),
+            depths: HashMap::new(),
+        };
+        for &entry in &self.entry_points {
+            if !instructions.contains_key(&(entry as usize)) {
+                return Err(ModuleError::InvalidEntryPoint(entry));
+            }
+            verifier.walk(ENTRY, entry as usize)?;
+        }
+
+        let mut offsets: Vec<usize> = instructions.keys().copied().collect();
+        offsets.sort();
+        match offsets.into_iter().find(|offset| !verifier.depths.contains_key(offset)) {
+            Some(offset) => Err(ModuleError::Unreachable(offset)),
+            None => Ok(()),
+        }
+    }
+}
+
+// The owner of instructions reached from an entry point rather than a Call.
+// Entry points all start on an empty stack, so they may share code.
+const ENTRY: usize = usize::MAX;
+
+// What a called function does to its caller's stack.
+#[derive(Clone, Copy)]
+struct Summary {
+    // How many of the caller's values it may pop.
+    arity: i64,
+    // The most values it adds above the caller's depth at the call.
+    peak: i64,
+    // The change in depth once it returns, or None if it always halts.
+    effect: Option<i64>,
+}
+
+struct Verifier<'m> {
+    module: &'m Module,
+    instructions: &'m HashMap<usize, OpCode>,
+    // The summary of every function verified so far, by its offset.
+    functions: HashMap<usize, Summary>,
+    // The chain of functions currently being verified, outermost first.
+    in_progress: Vec<usize>,
+    // Every instruction some path reaches: the function that owns it, or ENTRY,
+    // and the stack depth there relative to the start of that function.
+    depths: HashMap<usize, (usize, i64)>,
+}
+
+impl Verifier<'_> {
+    // Explores every path from `start`, tracking the stack depth relative to it.
+    // An entry point starts on an empty stack; a function on its caller's.
+    fn walk(&mut self, owner: usize, start: usize) -> Result<Summary, ModuleError> {
+        let code = &self.module.code;
+        let is_entry = owner == ENTRY;
+        let mut pending = vec![(start, 0i64)];
+        let mut summary = Summary { arity: 0, peak: 0, effect: None };
+
+        while let Some((at, depth)) = pending.pop() {
+            if let Some(&(reached_by, expected)) = self.depths.get(&at) {
+                if reached_by != owner {
+                    return Err(ModuleError::SharedCode(at));
+                }
+                if expected != depth {
+                    return Err(ModuleError::StackImbalance { at, expected, found: depth });
+                }
+                continue;
+            }
+            self.depths.insert(at, (owner, depth));
+            let opcode = self.instructions[&at];
+            self.check_immediate(opcode, at)?;
+
+            let next = at + 1 + opcode.immediate_len() + self.push_bytes_len(opcode, at);
+            // The lowest the stack gets during the instruction, the depth after it, and where it continues.
+            let (lowest, after, successors) = match opcode {
+                OpCode::Halt => (depth - 1, depth - 1, vec![]),
+                OpCode::Return => {
+                    if is_entry {
+                        return Err(ModuleError::ReturnOutsideCall(at));
+                    }
+                    match summary.effect {
+                        Some(expected) if expected != depth => {
+                            return Err(ModuleError::InconsistentReturn { function: start, expected, found: depth });
+                        }
+                        _ => summary.effect = Some(depth),
+                    }
+                    (depth, depth, vec![])
+                }
+                OpCode::Jump => (depth, depth, vec![self.target(at)]),
+                OpCode::JumpIf => (depth - 1, depth - 1, vec![next, self.target(at)]),
+                OpCode::Call => {
+                    let callee = self.function(self.target(at))?;
+                    summary.peak = summary.peak.max(depth + callee.peak);
+                    match callee.effect {
+                        Some(effect) => (depth - callee.arity, depth + effect, vec![next]),
+                        // The callee always halts, so nothing after the call runs.
+                        None => (depth - callee.arity, depth, vec![]),
+                    }
+                }
+                _ => {
+                    let (pops, pushes) = opcode.stack_effect();
+                    let low = depth - pops as i64;
+                    (low, low + pushes as i64, vec![next])
+                }
+            };
+            summary.arity = summary.arity.max(-lowest);
+            summary.peak = summary.peak.max(after);
+            if is_entry && summary.arity > 0 {
+                return Err(ModuleError::StackUnderflow(at));
+            }
+            if is_entry && summary.peak > self.module.max_stack as i64 {
+                return Err(ModuleError::StackTooDeep { at, max: self.module.max_stack });
+            }
+
+            for successor in successors {
+                if successor >= code.len() {
+                    return Err(ModuleError::RunsOffEnd(at));
+                }
+                pending.push((successor, after));
+            }
+        }
+        Ok(summary)
+    }
+
+    // Verifies the function at `start`, once, returning its summary.
+    fn function(&mut self, start: usize) -> Result<Summary, ModuleError> {
+        if let Some(summary) = self.functions.get(&start) {
+            return Ok(*summary);
+        }
+        if self.in_progress.contains(&start) {
+            return Err(ModuleError::Recursion(start));
+        }
+        if self.in_progress.len() >= MAX_CALL_DEPTH {
+            return Err(ModuleError::CallsTooDeep);
+        }
+        if self.functions.len() + self.in_progress.len() >= MAX_FUNCTIONS {
+            return Err(ModuleError::TooManyFunctions);
+        }
+        self.in_progress.push(start);
+        let summary = self.walk(start, start)?;
+        self.in_progress.pop();
+        self.functions.insert(start, summary);
+        Ok(summary)
+    }
+
+    fn target(&self, at: usize) -> usize {
+        bytecode::jump_target(&self.module.code, at).expect("decode_instructions checked the immediate")
+    }
+
+    // The inline bytes following a PushBytes' length byte.
+    fn push_bytes_len(&self, opcode: OpCode, at: usize) -> usize {
+        match opcode {
+            OpCode::PushBytes => self.module.code[at + 1] as usize,
+            _ => 0,
+        }
+    }
+
+    // Rejects immediates the ZVM would fail on: unknown locals, collections and constants.
+    fn check_immediate(&self, opcode: OpCode, at: usize) -> Result<(), ModuleError> {
+        let Some(&immediate) = self.module.code.get(at + 1) else {
+            return Ok(());
+        };
+        match opcode {
+            OpCode::LoadLocal | OpCode::StoreLocal if immediate as usize >= MAX_LOCALS => {
+                Err(VmError::InvalidLocal(immediate).into())
+            }
+            OpCode::Count if immediate > collection::LINKS => Err(VmError::InvalidCollection(opcode, immediate).into()),
+            OpCode::DataLen
+            | OpCode::DataWord
+            | OpCode::OwnerWord
+            | OpCode::IdWord
+            | OpCode::Data
+            | OpCode::Owner
+            | OpCode::Id
+                if immediate > collection::CURRENT =>
+            {
+                Err(VmError::InvalidCollection(opcode, immediate).into())
+            }
+            OpCode::LinkWord if immediate > 1 => Err(VmError::InvalidCollection(opcode, immediate).into()),
+            OpCode::PushConst if immediate as usize >= self.module.constants.len() => {
+                Err(ModuleError::InvalidConstant { opcode, at, index: immediate })
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,25 +1,82 @@
 use crate::chain::Chain;
-use crate::ledger::Block;
-use crate::state_db::StateDB;
+use crate::crypto::PublicKey;
+use crate::ledger::{Block, Transaction};
+use crate::state_db::{StateDB, StateError};
 use crate::validator::{TransactionValidator, ValidationError};
 use thiserror::Error;
 use crate::mempool::Mempool;
+use crate::peer_manager::PeerManager;
 use libp2p::ping;
 
-use crate::p2p::ZelealemBehaviour;
+use crate::p2p::{self, ChainId, ZelealemBehaviour};
 use libp2p::{
-    gossipsub, identity, mdns, noise, tcp, yamux, PeerId, Swarm, SwarmBuilder,
+    allow_block_list, gossipsub, identity, mdns, noise, tcp, yamux, PeerId, Swarm, SwarmBuilder,
 };
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
+use tokio::sync::broadcast;
 use crate::consensus::ValidatorSet;
+use crate::genesis::{ConsensusParams, Genesis, GenesisError};
+use crate::topics;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SubmitError {
+    #[error("Transaction is invalid: {0}")]
+    Invalid(#[from] ValidationError),
+    #[error("Input {0:?} is already spent by a transaction in the mempool")]
+    Conflict(crate::crypto::Hash),
+    #[error("Mempool is full")]
+    MempoolFull,
+}
 
 #[derive(Error, Debug)]
 pub enum ProcessBlockError {
     #[error("Block's previous_hash does not match the latest block in the chain")]
     MismatchedPreviousHash,
+    #[error("Block has {0} transactions, more than the consensus limit")]
+    TooManyTransactions(usize),
     #[error("Transaction validation failed: {0}")]
     TransactionError(#[from] ValidationError),
+    #[error("State Object {0:?} is spent by more than one transaction in the block")]
+    DoubleSpend(crate::crypto::Hash),
+    #[error("State Object {0:?} is created more than once, or already exists")]
+    DuplicateOutput(crate::crypto::Hash),
+    #[error("State Object {0:?} is referenced after an earlier transaction in the block spends it")]
+    SpentReference(crate::crypto::Hash),
+    #[error("Applying the block failed: {0}")]
+    StateError(#[from] StateError),
+}
+
+// The State Objects spent and created so far by the transactions of a block,
+// which are each validated against the state before the block.
+#[derive(Default)]
+struct BlockEffects {
+    spent: HashSet<crate::crypto::Hash>,
+    created: HashSet<crate::crypto::Hash>,
+}
+
+impl BlockEffects {
+    // Records `tx`'s effects, failing if it conflicts with an earlier transaction
+    // of the block or would recreate an object that already exists in `state`.
+    fn record(&mut self, state: &StateDB, tx: &Transaction) -> Result<(), ProcessBlockError> {
+        if let Some(id) = tx.inputs.iter().find(|id| self.spent.contains(*id)) {
+            return Err(ProcessBlockError::DoubleSpend(*id));
+        }
+        if let Some(id) = tx.reference_inputs.iter().find(|id| self.spent.contains(*id)) {
+            return Err(ProcessBlockError::SpentReference(*id));
+        }
+        if let Some(so) = tx
+            .outputs
+            .iter()
+            .find(|so| self.created.contains(&so.id) || state.get_so(&so.id).is_ok())
+        {
+            return Err(ProcessBlockError::DuplicateOutput(so.id));
+        }
+        self.spent.extend(tx.inputs.iter().copied());
+        self.created.extend(tx.outputs.iter().map(|so| so.id));
+        Ok(())
+    }
 }
 
 // CORRECTED: The Node does not own the runtime.
@@ -30,14 +87,25 @@ pub struct Node {
     pub id_keys: identity::Keypair,
     pub validator_set: ValidatorSet,
     pub mempool: Mempool,
+    pub peer_manager: PeerManager,
+    // The network this node belongs to; gossip for any other chain is rejected.
+    pub chain_id: ChainId,
+    pub consensus_params: ConsensusParams,
+    // Every block appended to the chain is announced here, with its height,
+    // e.g. for RPC subscribers.
+    pub block_notifier: broadcast::Sender<(u64, Block)>,
 }
 
 impl Node {
     // CORRECTED: Node::new is a true async function.
     pub async fn new() -> Self {
-        let id_keys = identity::Keypair::generate_ed25519();
+        Self::with_identity(identity::Keypair::generate_ed25519()).await
+    }
+
+    /// Creates a node that uses an existing libp2p identity, e.g. one loaded from disk.
+    pub async fn with_identity(id_keys: identity::Keypair) -> Self {
         let peer_id = PeerId::from(id_keys.public());
-        println!("Local peer ID: {}", peer_id);
+        log::info!("Local peer ID: {}", peer_id);
 
         let behaviour = {
             let message_id_fn = |message: &gossipsub::Message| {
@@ -45,17 +113,29 @@ impl Node {
                 message.data.hash(&mut s);
                 gossipsub::MessageId::from(s.finish().to_string())
             };
-            let gossipsub_config = gossipsub::ConfigBuilder::default().build().expect("Valid gossipsub config");
+            // Messages are held back from propagation until we have validated them
+            // and reported the outcome via `report_gossip_validation`.
+            let gossipsub_config = gossipsub::ConfigBuilder::default()
+                .validation_mode(gossipsub::ValidationMode::Strict)
+                .validate_messages()
+                .message_id_fn(message_id_fn)
+                .build()
+                .expect("Valid gossipsub config");
 
-            let gossipsub = gossipsub::Behaviour::new(
+            let mut gossipsub = gossipsub::Behaviour::new(
                 gossipsub::MessageAuthenticity::Signed(id_keys.clone()),
                 gossipsub_config,
             )
             .expect("Correct gossipsub");
+            let (score_params, score_thresholds) = crate::p2p::peer_score_config();
+            gossipsub
+                .with_peer_score(score_params, score_thresholds)
+                .expect("Valid peer score config");
 
             let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), peer_id).unwrap();
             let ping = ping::Behaviour::new(ping::Config::new());
-            ZelealemBehaviour { gossipsub, mdns, ping }
+            let blocked_peers = allow_block_list::Behaviour::default();
+            ZelealemBehaviour { gossipsub, mdns, ping, blocked_peers }
         };
         
         // CORRECTED: Swarm is built directly in the async context.
@@ -78,32 +158,216 @@ impl Node {
             swarm,
             validator_set: ValidatorSet::new(),
             mempool: Mempool::new(),
+            peer_manager: PeerManager::default(),
+            chain_id: p2p::DEFAULT_CHAIN_ID,
+            consensus_params: ConsensusParams::default(),
+            block_notifier: broadcast::channel(64).0,
             id_keys,
         }
     }
     
-    // Unchanged function
+    /// Creates a node whose chain, state and validator set are derived from `genesis`.
+    pub async fn with_genesis(id_keys: identity::Keypair, genesis: &Genesis) -> Result<Self, GenesisError> {
+        let state = genesis.build()?;
+        let mut node = Self::with_identity(id_keys).await;
+        node.chain = state.chain;
+        node.state_db = state.state_db;
+        node.validator_set = state.validator_set;
+        node.chain_id = genesis.chain_id;
+        node.consensus_params = genesis.consensus.clone();
+        Ok(node)
+    }
+
+    // A validator for transactions against the current state, under this chain's
+    // rules, for inclusion in the next block.
+    fn transaction_validator(&self) -> TransactionValidator<'_> {
+        TransactionValidator::new(&self.state_db)
+            .with_gas_limit(self.consensus_params.max_tx_gas)
+            .with_block_height(self.chain.height() + 1)
+    }
+
     pub fn process_block(&mut self, block: Block) -> Result<(), ProcessBlockError> {
         let latest_hash = self.chain.get_latest_hash();
         if block.previous_hash != latest_hash {
             return Err(ProcessBlockError::MismatchedPreviousHash);
         }
+        if block.transactions.len() as u64 > self.consensus_params.max_txs_per_block {
+            return Err(ProcessBlockError::TooManyTransactions(block.transactions.len()));
+        }
 
-        let validator = TransactionValidator::new(&self.state_db);
+        let validator = self.transaction_validator();
+        let mut effects = BlockEffects::default();
         for tx in &block.transactions {
             validator.validate_transaction(tx)?;
+            effects.record(&self.state_db, tx)?;
         }
 
+        // Apply the block to a copy of the state, so a failure part way leaves ours untouched.
+        let mut staged = self.state_db.clone();
         for tx in &block.transactions {
             for input_id in &tx.inputs {
-                self.state_db.remove_so(input_id).unwrap();
+                staged.remove_so(input_id)?;
             }
             for output_so in &tx.outputs {
-                self.state_db.add_so(output_so.clone()).unwrap();
+                staged.add_so(output_so.clone())?;
             }
         }
+        self.state_db = staged;
+        self.mempool.remove_spent(&effects.spent);
 
         self.chain.add_block(block);
+        if self.block_notifier.receiver_count() > 0 {
+            let latest = self.chain.get_latest_block().unwrap().clone();
+            let _ = self.block_notifier.send((self.chain.height(), latest));
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Builds the next block for `proposer` from up to `max_txs` mempool
+    /// transactions. Each is validated again, after the ones before it, and
+    /// dropped if the state has moved on or it conflicts with them.
+    pub fn propose_block(&mut self, proposer: PublicKey, max_txs: usize) -> Block {
+        let candidates = self.mempool.get_batch(max_txs);
+        let validator = self.transaction_validator();
+        let mut effects = BlockEffects::default();
+        let mut transactions = Vec::with_capacity(candidates.len());
+        for tx in candidates {
+            let checked = validator
+                .validate_transaction(&tx)
+                .map_err(ProcessBlockError::from)
+                .and_then(|_| effects.record(&self.state_db, &tx));
+            match checked {
+                Ok(()) => transactions.push(tx),
+                Err(e) => log::warn!("Dropping transaction {:?} from the proposal: {}", tx.id, e),
+            }
+        }
+        Block::new(self.chain.get_latest_hash(), proposer, transactions, vec![])
+    }
+
+    /// Accepts a transaction from a local client: validates it, adds it to the
+    /// mempool and gossips it to the network. Returns the transaction's id.
+    pub fn submit_transaction(&mut self, tx: Transaction) -> Result<crate::crypto::Hash, SubmitError> {
+        self.transaction_validator().validate_transaction(&tx)?;
+        if let Some(input) = self.mempool.conflicting_input(&tx) {
+            return Err(SubmitError::Conflict(input));
+        }
+
+        let tx_id = tx.id;
+        let serialized_tx = p2p::encode_transaction(self.chain_id, &tx);
+        if !self.mempool.add_transaction(tx) {
+            return Err(SubmitError::MempoolFull);
+        }
+        // Publishing fails when we have no peers yet; the transaction still
+        // sits in our own mempool and will be included when we propose.
+        if let Err(e) = self.swarm.behaviour_mut().gossipsub.publish(topics::transactions_topic(), serialized_tx) {
+            log::debug!("Could not gossip submitted transaction: {:?}", e);
+        }
+        Ok(tx_id)
+    }
+
+    /// Validates a message received over gossipsub and applies it if it is acceptable:
+    /// transactions go into the mempool, blocks are appended to the chain.
+    /// The returned verdict must be handed to `report_gossip_validation`.
+    pub fn handle_gossip_message(&mut self, message: &gossipsub::Message) -> gossipsub::MessageAcceptance {
+        if message.topic == topics::transactions_topic().hash() {
+            self.handle_transaction_message(&message.data)
+        } else if message.topic == topics::blocks_topic().hash() {
+            self.handle_block_message(&message.data)
+        } else {
+            // We never subscribed to this topic; drop it without blaming the sender.
+            gossipsub::MessageAcceptance::Ignore
+        }
+    }
+
+    fn handle_transaction_message(&mut self, data: &[u8]) -> gossipsub::MessageAcceptance {
+        let tx = match p2p::decode_transaction(data, self.chain_id) {
+            Ok(tx) => tx,
+            Err(e) => {
+                log::warn!("Failed to decode transaction: {}", e);
+                return gossipsub::MessageAcceptance::Reject;
+            }
+        };
+
+        let validator = self.transaction_validator();
+        match validator.validate_transaction(&tx) {
+            // The first of two conflicting transactions wins; the other may be an honest race.
+            Ok(_) if self.mempool.conflicting_input(&tx).is_some() => gossipsub::MessageAcceptance::Ignore,
+            Ok(_) => {
+                if self.mempool.add_transaction(tx) {
+                    gossipsub::MessageAcceptance::Accept
+                } else {
+                    gossipsub::MessageAcceptance::Ignore
+                }
+            }
+            // An input, reference or link end may simply have been spent by a block we saw first.
+            Err(ValidationError::InputNotFound(_))
+            | Err(ValidationError::ReferenceNotFound(_))
+            | Err(ValidationError::DanglingCausalLink(_))
+            | Err(ValidationError::StateError(_)) => {
+                gossipsub::MessageAcceptance::Ignore
+            }
+            // Scripts can depend on the block height, so the sender may have checked
+            // them against a neighbouring height. Drop the transaction without a strike.
+            Err(e @ ValidationError::InputRejected(_))
+            | Err(e @ ValidationError::CausalLinkRejected(_))
+            | Err(e @ ValidationError::ScriptFailed { .. }) => {
+                log::debug!("Transaction rejected by a script: {}", e);
+                gossipsub::MessageAcceptance::Ignore
+            }
+            Err(e) => {
+                log::warn!("Invalid transaction received: {}", e);
+                gossipsub::MessageAcceptance::Reject
+            }
+        }
+    }
+
+    fn handle_block_message(&mut self, data: &[u8]) -> gossipsub::MessageAcceptance {
+        let block = match p2p::decode_block(data, self.chain_id) {
+            Ok(block) => block,
+            Err(e) => {
+                log::warn!("Failed to decode block: {}", e);
+                return gossipsub::MessageAcceptance::Reject;
+            }
+        };
+
+        match self.process_block(block) {
+            Ok(_) => gossipsub::MessageAcceptance::Accept,
+            // We may be behind or on a different fork; that is not the sender's fault.
+            Err(ProcessBlockError::MismatchedPreviousHash) => gossipsub::MessageAcceptance::Ignore,
+            Err(e) => {
+                log::warn!("Invalid block received: {}", e);
+                gossipsub::MessageAcceptance::Reject
+            }
+        }
+    }
+
+    /// Reports our validation verdict to gossipsub, which feeds its peer scoring,
+    /// and bans the propagating peer once it has sent too many invalid messages.
+    pub fn report_gossip_validation(
+        &mut self,
+        message_id: &gossipsub::MessageId,
+        propagation_source: &PeerId,
+        acceptance: gossipsub::MessageAcceptance,
+    ) {
+        let rejected = matches!(acceptance, gossipsub::MessageAcceptance::Reject);
+        let _ = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(message_id, propagation_source, acceptance);
+
+        if rejected && self.peer_manager.record_invalid_message(*propagation_source) {
+            log::warn!("Banning peer {} for repeatedly sending invalid messages.", propagation_source);
+            self.ban_peer(propagation_source);
+        }
+    }
+
+    /// Disconnects from `peer` and refuses any future connections from it.
+    pub fn ban_peer(&mut self, peer: &PeerId) {
+        let behaviour = self.swarm.behaviour_mut();
+        behaviour.gossipsub.blacklist_peer(peer);
+        behaviour.gossipsub.remove_explicit_peer(peer);
+        behaviour.blocked_peers.block_peer(*peer);
+        let _ = self.swarm.disconnect_peer_id(*peer);
+    }
+}
@@ -1,4 +1,10 @@
-use libp2p::{gossipsub, mdns, ping, swarm::NetworkBehaviour}; // Add ping
+use libp2p::{allow_block_list, gossipsub, mdns, ping, swarm::NetworkBehaviour}; // Add ping
+use crate::ledger::{Block, Transaction};
+use crate::topics;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
 
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "ZelealemBehaviourEvent")] // We need to specify the event type
@@ -6,6 +12,8 @@ pub struct ZelealemBehaviour {
     pub gossipsub: gossipsub::Behaviour,
     pub mdns: mdns::tokio::Behaviour,
     pub ping: ping::Behaviour, // Add the ping protocol
+    // Refuses (and closes) connections to peers we have banned for misbehaviour.
+    pub blocked_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
 }
 
 // Define the custom event our behaviour can emit
@@ -32,4 +40,230 @@ impl From<ping::Event> for ZelealemBehaviourEvent {
     fn from(event: ping::Event) -> Self {
         ZelealemBehaviourEvent::Ping(event)
     }
-}
\ No newline at end of file
+}
+
+// The block list never emits events, but the derive macro still needs the conversion.
+impl From<void::Void> for ZelealemBehaviourEvent {
+    fn from(event: void::Void) -> Self {
+        void::unreachable(event)
+    }
+}
+
+/// Builds the gossipsub peer scoring configuration.
+/// Every message we reject during validation counts as an invalid delivery, and
+/// that penalty is weighted heavily enough that a handful of bad messages pushes
+/// a peer below the graylist threshold, after which gossipsub ignores it.
+pub fn peer_score_config() -> (gossipsub::PeerScoreParams, gossipsub::PeerScoreThresholds) {
+    let topic_params = gossipsub::TopicScoreParams {
+        topic_weight: 1.0,
+        invalid_message_deliveries_weight: -100.0,
+        invalid_message_deliveries_decay: 0.5,
+        // Traffic on a young network is sparse, so quiet mesh peers are not penalised.
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        ..Default::default()
+    };
+
+    let mut topics = HashMap::new();
+    topics.insert(topics::transactions_topic().hash(), topic_params.clone());
+    topics.insert(topics::blocks_topic().hash(), topic_params);
+
+    let params = gossipsub::PeerScoreParams {
+        topics,
+        // We don't run behind NATs that share IPs between honest nodes yet,
+        // so colocation is not treated as suspicious.
+        ip_colocation_factor_weight: 0.0,
+        decay_interval: Duration::from_secs(1),
+        ..Default::default()
+    };
+
+    let thresholds = gossipsub::PeerScoreThresholds {
+        gossip_threshold: -100.0,
+        publish_threshold: -200.0,
+        graylist_threshold: -400.0,
+        ..Default::default()
+    };
+
+    (params, thresholds)
+}
+
+// --- Wire protocol ---
+//
+// Every gossiped message is wrapped in a fixed-layout envelope so that nodes can
+// reject traffic from other networks and from incompatible software versions
+// before touching the payload. All integers are little-endian.
+//
+//   offset  size  field
+//   0       4     magic ("ZLM" followed by 0x00)
+//   4       4     chain id
+//   8       2     protocol version
+//   10      1     message kind
+//   11      4     payload length
+//   15      n     payload (bincode, standard config)
+
+pub const PROTOCOL_MAGIC: [u8; 4] = *b"ZLM\0";
+// The envelope version we produce.
+// v2: transactions carry a list of signatures and the multisig policies of their inputs.
+// v3: each input is authorized by its own witness (signatures and optional policy).
+// v4: transactions carry reference inputs.
+pub const PROTOCOL_VERSION: u16 = 4;
+// The oldest envelope version we still understand.
+pub const MIN_PROTOCOL_VERSION: u16 = 4;
+
+// Identifies the network a message belongs to, so testnets and mainnets never mix.
+pub type ChainId = u32;
+pub const DEFAULT_CHAIN_ID: ChainId = 1;
+
+const ENVELOPE_HEADER_LEN: usize = 15;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EnvelopeError {
+    #[error("Message is {0} bytes, shorter than the envelope header")]
+    TooShort(usize),
+    #[error("Message does not start with the Zelealem protocol magic")]
+    BadMagic,
+    #[error("Message is for chain {found}, but this node is on chain {expected}")]
+    WrongChain { expected: ChainId, found: ChainId },
+    #[error("Unsupported protocol version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Unknown message kind {0}")]
+    UnknownKind(u8),
+    #[error("Expected a {expected:?} message, got {found:?}")]
+    UnexpectedKind { expected: MessageKind, found: MessageKind },
+    #[error("Payload length field says {declared} bytes, but {actual} bytes follow")]
+    LengthMismatch { declared: usize, actual: usize },
+    #[error("Failed to decode payload: {0}")]
+    Payload(String),
+}
+
+// The kind of object carried in an envelope's payload.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Transaction = 0x01,
+    Block = 0x02,
+}
+
+impl TryFrom<u8> for MessageKind {
+    type Error = EnvelopeError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            0x01 => Ok(MessageKind::Transaction),
+            0x02 => Ok(MessageKind::Block),
+            _ => Err(EnvelopeError::UnknownKind(byte)),
+        }
+    }
+}
+
+// A framed gossip message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    pub chain_id: ChainId,
+    pub version: u16,
+    pub kind: MessageKind,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// Wraps `payload` in an envelope for the current protocol version.
+    pub fn new(chain_id: ChainId, kind: MessageKind, payload: Vec<u8>) -> Self {
+        Self {
+            chain_id,
+            version: PROTOCOL_VERSION,
+            kind,
+            payload,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENVELOPE_HEADER_LEN + self.payload.len());
+        bytes.extend_from_slice(&PROTOCOL_MAGIC);
+        bytes.extend_from_slice(&self.chain_id.to_le_bytes());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(self.kind as u8);
+        bytes.extend_from_slice(&(self.payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Parses an envelope, rejecting anything not addressed to `expected_chain_id`
+    /// or framed with a protocol version we don't support.
+    pub fn decode(bytes: &[u8], expected_chain_id: ChainId) -> Result<Self, EnvelopeError> {
+        if bytes.len() < ENVELOPE_HEADER_LEN {
+            return Err(EnvelopeError::TooShort(bytes.len()));
+        }
+        if bytes[0..4] != PROTOCOL_MAGIC {
+            return Err(EnvelopeError::BadMagic);
+        }
+
+        let chain_id = ChainId::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if chain_id != expected_chain_id {
+            return Err(EnvelopeError::WrongChain { expected: expected_chain_id, found: chain_id });
+        }
+
+        let version = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+
+        let kind = MessageKind::try_from(bytes[10])?;
+
+        let declared = u32::from_le_bytes(bytes[11..15].try_into().unwrap()) as usize;
+        let payload = &bytes[ENVELOPE_HEADER_LEN..];
+        if payload.len() != declared {
+            return Err(EnvelopeError::LengthMismatch { declared, actual: payload.len() });
+        }
+
+        Ok(Self {
+            chain_id,
+            version,
+            kind,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+fn encode_message<T: Serialize>(chain_id: ChainId, kind: MessageKind, value: &T) -> Vec<u8> {
+    let payload = bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .expect("Failed to serialize gossip payload");
+    Envelope::new(chain_id, kind, payload).encode()
+}
+
+fn decode_message<T: DeserializeOwned>(
+    bytes: &[u8],
+    chain_id: ChainId,
+    kind: MessageKind,
+) -> Result<T, EnvelopeError> {
+    let envelope = Envelope::decode(bytes, chain_id)?;
+    if envelope.kind != kind {
+        return Err(EnvelopeError::UnexpectedKind { expected: kind, found: envelope.kind });
+    }
+    let (value, read) = bincode::serde::decode_from_slice::<T, _>(&envelope.payload, bincode::config::standard())
+        .map_err(|e| EnvelopeError::Payload(e.to_string()))?;
+    if read != envelope.payload.len() {
+        return Err(EnvelopeError::Payload(format!(
+            "{} trailing bytes after payload",
+            envelope.payload.len() - read
+        )));
+    }
+    Ok(value)
+}
+
+/// Serializes a transaction into a gossip envelope.
+pub fn encode_transaction(chain_id: ChainId, tx: &Transaction) -> Vec<u8> {
+    encode_message(chain_id, MessageKind::Transaction, tx)
+}
+
+/// Serializes a block into a gossip envelope.
+pub fn encode_block(chain_id: ChainId, block: &Block) -> Vec<u8> {
+    encode_message(chain_id, MessageKind::Block, block)
+}
+
+pub fn decode_transaction(bytes: &[u8], chain_id: ChainId) -> Result<Transaction, EnvelopeError> {
+    decode_message(bytes, chain_id, MessageKind::Transaction)
+}
+
+pub fn decode_block(bytes: &[u8], chain_id: ChainId) -> Result<Block, EnvelopeError> {
+    decode_message(bytes, chain_id, MessageKind::Block)
+}
@@ -0,0 +1,58 @@
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+
+// How many invalid or undecodable messages a peer may send before we ban it.
+pub const DEFAULT_BAN_THRESHOLD: u32 = 3;
+
+// The PeerManager keeps track of misbehaving peers.
+// Gossipsub's own peer scoring already de-prioritises peers that send invalid
+// messages; this is the harder backstop that disconnects and bans repeat offenders.
+#[derive(Debug)]
+pub struct PeerManager {
+    /// The number of invalid messages received from each peer.
+    strikes: HashMap<PeerId, u32>,
+    /// Peers that have crossed the ban threshold.
+    banned: HashSet<PeerId>,
+    ban_threshold: u32,
+}
+
+impl Default for PeerManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_BAN_THRESHOLD)
+    }
+}
+
+impl PeerManager {
+    pub fn new(ban_threshold: u32) -> Self {
+        Self {
+            strikes: HashMap::new(),
+            banned: HashSet::new(),
+            ban_threshold,
+        }
+    }
+
+    /// Records an invalid message from `peer`.
+    /// Returns `true` exactly once: when this strike pushes the peer over the ban threshold.
+    pub fn record_invalid_message(&mut self, peer: PeerId) -> bool {
+        if self.banned.contains(&peer) {
+            return false;
+        }
+        let strikes = self.strikes.entry(peer).or_insert(0);
+        *strikes += 1;
+        if *strikes >= self.ban_threshold {
+            self.strikes.remove(&peer);
+            self.banned.insert(peer);
+            return true;
+        }
+        false
+    }
+
+    /// Returns the number of strikes currently held against `peer`.
+    pub fn strikes(&self, peer: &PeerId) -> u32 {
+        self.strikes.get(peer).copied().unwrap_or(0)
+    }
+
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.banned.contains(peer)
+    }
+}
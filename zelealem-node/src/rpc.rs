@@ -0,0 +1,373 @@
+use crate::address::AddressError;
+use crate::consensus::Stake;
+use crate::crypto::{Hash, PublicKey};
+use crate::ledger::{Block, CausalLink, StateObject, Transaction};
+use crate::node::{Node, SubmitError};
+use crate::p2p::ChainId;
+use jsonrpsee::server::{RpcModule, Server, ServerHandle, SubscriptionMessage};
+use jsonrpsee::types::ErrorObjectOwned;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use thiserror::Error;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+// JSON-RPC API
+//
+// The server runs on its own task, but the `Node` is owned by the main event
+// loop. Handlers therefore never touch the node directly: they send a
+// `NodeQuery` closure over a channel, the event loop runs it against the node
+// and the answer comes back over a oneshot channel. HTTP and WebSocket are
+// served on the same port; subscriptions require WebSocket.
+//
+// Hashes and byte strings are hex-encoded; public keys are addresses on the
+// node's chain (see `address`), and addresses for other chains are refused.
+// Transactions are submitted as the hex encoding of their bincode
+// serialization, exactly as they are gossiped.
+
+pub const DEFAULT_RPC_ADDR: &str = "127.0.0.1:9933";
+
+// Application error codes, in the range JSON-RPC reserves for servers.
+pub const INVALID_TRANSACTION: i32 = -32000;
+pub const MEMPOOL_FULL: i32 = -32001;
+pub const NODE_UNAVAILABLE: i32 = -32002;
+const INVALID_PARAMS: i32 = -32602;
+
+// A request for the event loop to run against the node.
+pub type NodeQuery = Box<dyn FnOnce(&mut Node) + Send>;
+
+// Shared state for every RPC handler.
+pub struct RpcContext {
+    queries: mpsc::Sender<NodeQuery>,
+    blocks: broadcast::Sender<(u64, Block)>,
+    chain_id: ChainId,
+}
+
+impl RpcContext {
+    /// `queries` is drained by the node's event loop; `blocks` is the node's `block_notifier`.
+    pub fn new(queries: mpsc::Sender<NodeQuery>, blocks: broadcast::Sender<(u64, Block)>, chain_id: ChainId) -> Self {
+        Self { queries, blocks, chain_id }
+    }
+
+    // Runs `f` against the node on the event loop and waits for its answer.
+    async fn call<T, F>(&self, f: F) -> Result<T, ErrorObjectOwned>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Node) -> T + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let query: NodeQuery = Box::new(move |node| {
+            let _ = tx.send(f(node));
+        });
+        let unavailable = || error(NODE_UNAVAILABLE, "Node is shutting down");
+        self.queries.send(query).await.map_err(|_| unavailable())?;
+        rx.await.map_err(|_| unavailable())
+    }
+}
+
+// --- Response types ---
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StateObjectView {
+    pub id: String,
+    pub owner: String,
+    pub data: String,
+    pub validation_logic: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CausalLinkView {
+    pub source_so_id: String,
+    pub target_so_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionView {
+    pub id: String,
+    pub inputs: Vec<String>,
+    pub reference_inputs: Vec<String>,
+    pub outputs: Vec<StateObjectView>,
+    pub causal_links: Vec<CausalLinkView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockView {
+    pub id: String,
+    pub height: u64,
+    pub previous_hash: String,
+    pub proposer: String,
+    pub transactions: Vec<TransactionView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainTip {
+    pub height: u64,
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MempoolStatus {
+    pub size: usize,
+    pub capacity: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorView {
+    pub pub_key: String,
+    pub stake: Stake,
+}
+
+impl StateObjectView {
+    pub fn new(so: &StateObject, chain_id: ChainId) -> Self {
+        Self {
+            id: hex::encode(so.id),
+            owner: so.owner.to_address(chain_id),
+            data: hex::encode(&so.data),
+            validation_logic: hex::encode(&so.validation_logic),
+        }
+    }
+
+    // Lets clients turn an RPC answer back into a ledger object. The id is recomputed
+    // rather than trusted, so a misbehaving node cannot pass off altered content.
+    pub fn to_state_object(&self, chain_id: ChainId) -> Result<StateObject, ViewError> {
+        let owner = PublicKey::from_address(&self.owner, chain_id)?;
+        let data = hex::decode(&self.data).map_err(|_| ViewError::InvalidField("data"))?;
+        let logic = hex::decode(&self.validation_logic).map_err(|_| ViewError::InvalidField("validation_logic"))?;
+        let so = StateObject::new(owner, data, logic);
+        if so.id != decode_field::<32>("id", &self.id)? {
+            return Err(ViewError::MismatchedId);
+        }
+        Ok(so)
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ViewError {
+    #[error("Field {0} is not valid hex of the expected length")]
+    InvalidField(&'static str),
+    #[error("Invalid owner: {0}")]
+    InvalidOwner(#[from] AddressError),
+    #[error("State Object id does not match its content")]
+    MismatchedId,
+}
+
+fn decode_field<const N: usize>(field: &'static str, value: &str) -> Result<[u8; N], ViewError> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ViewError::InvalidField(field))
+}
+
+impl From<&CausalLink> for CausalLinkView {
+    fn from(link: &CausalLink) -> Self {
+        Self {
+            source_so_id: hex::encode(link.source_so_id),
+            target_so_id: hex::encode(link.target_so_id),
+        }
+    }
+}
+
+impl TransactionView {
+    pub fn new(tx: &Transaction, chain_id: ChainId) -> Self {
+        Self {
+            id: hex::encode(tx.id),
+            inputs: tx.inputs.iter().map(hex::encode).collect(),
+            reference_inputs: tx.reference_inputs.iter().map(hex::encode).collect(),
+            outputs: tx.outputs.iter().map(|so| StateObjectView::new(so, chain_id)).collect(),
+            causal_links: tx.causal_links.iter().map(CausalLinkView::from).collect(),
+        }
+    }
+}
+
+impl BlockView {
+    pub fn new(block: &Block, height: u64, chain_id: ChainId) -> Self {
+        Self {
+            id: hex::encode(block.id),
+            height,
+            previous_hash: hex::encode(block.previous_hash),
+            proposer: block.proposer.to_address(chain_id),
+            transactions: block.transactions.iter().map(|tx| TransactionView::new(tx, chain_id)).collect(),
+        }
+    }
+}
+
+// --- Helpers ---
+
+fn error(code: i32, message: impl Into<String>) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(code, message.into(), None::<()>)
+}
+
+fn parse_bytes32(s: &str) -> Result<[u8; 32], ErrorObjectOwned> {
+    hex::decode(s)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| error(INVALID_PARAMS, format!("Expected 32 hex-encoded bytes, got {:?}", s)))
+}
+
+fn parse_address(s: &str, chain_id: ChainId) -> Result<PublicKey, ErrorObjectOwned> {
+    PublicKey::from_address(s, chain_id).map_err(|e| error(INVALID_PARAMS, e.to_string()))
+}
+
+/// Encodes a transaction the way `zel_submitTransaction` expects it.
+pub fn encode_transaction_hex(tx: &Transaction) -> String {
+    hex::encode(bincode::serde::encode_to_vec(tx, bincode::config::standard()).expect("Failed to serialize TX"))
+}
+
+pub fn decode_transaction_hex(tx_hex: &str) -> Result<Transaction, String> {
+    let bytes = hex::decode(tx_hex.trim()).map_err(|e| e.to_string())?;
+    let (tx, _) = bincode::serde::decode_from_slice::<Transaction, _>(&bytes, bincode::config::standard())
+        .map_err(|e| format!("Malformed transaction: {}", e))?;
+    Ok(tx)
+}
+
+// --- Module ---
+
+/// Builds the RPC module with every method and subscription registered.
+pub fn rpc_module(ctx: RpcContext) -> RpcModule<RpcContext> {
+    let mut module = RpcModule::new(ctx);
+
+    module
+        .register_async_method("zel_submitTransaction", |params, ctx, _| async move {
+            let tx_hex: String = params.one()?;
+            let tx = decode_transaction_hex(&tx_hex).map_err(|e| error(INVALID_PARAMS, e))?;
+            match ctx.call(move |node| node.submit_transaction(tx)).await? {
+                Ok(id) => Ok(hex::encode(id)),
+                Err(e @ (SubmitError::Invalid(_) | SubmitError::Conflict(_))) => Err(error(INVALID_TRANSACTION, e.to_string())),
+                Err(e @ SubmitError::MempoolFull) => Err(error(MEMPOOL_FULL, e.to_string())),
+            }
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_getBlockByHash", |params, ctx, _| async move {
+            let hash: Hash = parse_bytes32(&params.one::<String>()?)?;
+            let chain_id = ctx.chain_id;
+            ctx.call(move |node| {
+                let height = node.chain.height_of(&hash)?;
+                node.chain.get_block(height).map(|b| BlockView::new(b, height, chain_id))
+            })
+            .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_getBlockByHeight", |params, ctx, _| async move {
+            let height: u64 = params.one()?;
+            let chain_id = ctx.chain_id;
+            ctx.call(move |node| node.chain.get_block(height).map(|b| BlockView::new(b, height, chain_id)))
+                .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_getStateObject", |params, ctx, _| async move {
+            let id: Hash = parse_bytes32(&params.one::<String>()?)?;
+            let chain_id = ctx.chain_id;
+            ctx.call(move |node| node.state_db.get_so(&id).ok().map(|so| StateObjectView::new(so, chain_id)))
+                .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_getObjectsByOwner", |params, ctx, _| async move {
+            let chain_id = ctx.chain_id;
+            let owner = parse_address(&params.one::<String>()?, chain_id)?;
+            ctx.call(move |node| {
+                node.state_db
+                    .objects_by_owner(&owner)
+                    .into_iter()
+                    .map(|so| StateObjectView::new(so, chain_id))
+                    .collect::<Vec<_>>()
+            })
+            .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_getBalance", |params, ctx, _| async move {
+            let owner = parse_address(&params.one::<String>()?, ctx.chain_id)?;
+            ctx.call(move |node| node.state_db.balance_of(&owner)).await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_chainTip", |_, ctx, _| async move {
+            ctx.call(|node| ChainTip {
+                height: node.chain.height(),
+                hash: hex::encode(node.chain.get_latest_hash()),
+            })
+            .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_method("zel_chainId", |_, ctx, _| ctx.chain_id)
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_mempoolStatus", |_, ctx, _| async move {
+            ctx.call(|node| MempoolStatus {
+                size: node.mempool.len(),
+                capacity: node.mempool.capacity(),
+            })
+            .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_async_method("zel_validators", |_, ctx, _| async move {
+            let chain_id = ctx.chain_id;
+            ctx.call(move |node| {
+                let mut validators: Vec<_> = node.validator_set.validators.values().collect();
+                validators.sort_by_key(|v| v.pub_key);
+                validators
+                    .into_iter()
+                    .map(|v| ValidatorView {
+                        pub_key: v.pub_key.to_address(chain_id),
+                        stake: v.stake,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+        })
+        .expect("Method names are unique");
+
+    module
+        .register_subscription(
+            "zel_subscribeNewBlocks",
+            "zel_newBlock",
+            "zel_unsubscribeNewBlocks",
+            |_, pending, ctx, _| async move {
+                let mut blocks = ctx.blocks.subscribe();
+                let sink = pending.accept().await?;
+                loop {
+                    tokio::select! {
+                        _ = sink.closed() => return Ok(()),
+                        received = blocks.recv() => match received {
+                            Ok((height, block)) => {
+                                let message = SubscriptionMessage::from_json(&BlockView::new(&block, height, ctx.chain_id))?;
+                                if sink.send(message).await.is_err() {
+                                    return Ok(());
+                                }
+                            }
+                            // A slow subscriber missed some blocks; keep streaming the rest.
+                            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                        },
+                    }
+                }
+            },
+        )
+        .expect("Method names are unique");
+
+    module
+}
+
+/// Starts the JSON-RPC server on `addr`.
+/// Returns the address actually bound (useful when `addr` uses port 0) and a handle
+/// that keeps the server running until it is stopped or dropped.
+pub async fn start_server(addr: SocketAddr, ctx: RpcContext) -> std::io::Result<(SocketAddr, ServerHandle)> {
+    let server = Server::builder().build(addr).await?;
+    let local_addr = server.local_addr()?;
+    let handle = server.start(rpc_module(ctx));
+    Ok((local_addr, handle))
+}
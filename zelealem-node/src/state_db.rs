@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::ledger::StateObject;
-use crate::crypto::Hash;
+use crate::crypto::{self, Hash, PublicKey};
 use thiserror::Error;
 
 // Define custom errors for our database operations for clearer error handling.
@@ -14,17 +14,19 @@ pub enum StateError {
 
 // StateDB is our in-memory key-value store for State Objects.
 // The key is the StateObject's unique Hash (ID), and the value is the SO itself.
+// Secondary indexes map owners and validation-logic hashes to the IDs of the
+// objects they cover; they are kept in step with `objects` on every add and remove.
 #[derive(Default, Clone)]
 pub struct StateDB {
     objects: HashMap<Hash, StateObject>,
+    by_owner: HashMap<PublicKey, HashSet<Hash>>,
+    by_logic: HashMap<Hash, HashSet<Hash>>,
 }
 
 impl StateDB {
     // Creates a new, empty state database.
     pub fn new() -> Self {
-        Self {
-            objects: HashMap::new(),
-        }
+        Self::default()
     }
 
     // Adds a State Object to the database.
@@ -33,6 +35,11 @@ impl StateDB {
         if self.objects.contains_key(&so.id) {
             return Err(StateError::AlreadyExists(so.id));
         }
+        self.by_owner.entry(so.owner).or_default().insert(so.id);
+        self.by_logic
+            .entry(crypto::hash_data(&so.validation_logic))
+            .or_default()
+            .insert(so.id);
         self.objects.insert(so.id, so);
         Ok(())
     }
@@ -46,6 +53,51 @@ impl StateDB {
     // Removes a State Object from the database, consuming it.
     // Returns the removed object or an error if it was not found.
     pub fn remove_so(&mut self, id: &Hash) -> Result<StateObject, StateError> {
-        self.objects.remove(id).ok_or(StateError::NotFound(*id))
+        let so = self.objects.remove(id).ok_or(StateError::NotFound(*id))?;
+        remove_from_index(&mut self.by_owner, &so.owner, id);
+        remove_from_index(&mut self.by_logic, &crypto::hash_data(&so.validation_logic), id);
+        Ok(so)
+    }
+
+    // Returns every State Object owned by `owner`, in no particular order.
+    pub fn objects_by_owner(&self, owner: &PublicKey) -> Vec<&StateObject> {
+        self.lookup(self.by_owner.get(owner))
+    }
+
+    // Returns every State Object whose validation logic hashes to `logic_hash`,
+    // e.g. all instances of one contract.
+    pub fn objects_by_logic(&self, logic_hash: &Hash) -> Vec<&StateObject> {
+        self.lookup(self.by_logic.get(logic_hash))
+    }
+
+    // Sums the amounts of all value objects owned by `owner`.
+    // Objects that don't hold a plain amount (see `StateObject::amount`) are ignored.
+    pub fn balance_of(&self, owner: &PublicKey) -> u64 {
+        self.objects_by_owner(owner)
+            .iter()
+            .filter_map(|so| so.amount())
+            .fold(0, u64::saturating_add)
+    }
+
+    fn lookup(&self, ids: Option<&HashSet<Hash>>) -> Vec<&StateObject> {
+        ids.into_iter()
+            .flatten()
+            .filter_map(|id| self.objects.get(id))
+            .collect()
+    }
+
+    // Iterates over every State Object in the database, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &StateObject> {
+        self.objects.values()
+    }
+}
+
+// Removes `id` from the set stored under `key`, dropping the set once it is empty.
+fn remove_from_index<K: std::hash::Hash + Eq>(index: &mut HashMap<K, HashSet<Hash>>, key: &K, id: &Hash) {
+    if let Some(ids) = index.get_mut(key) {
+        ids.remove(id);
+        if ids.is_empty() {
+            index.remove(key);
+        }
     }
-}
\ No newline at end of file
+}
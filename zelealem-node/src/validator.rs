@@ -1,6 +1,8 @@
-use crate::crypto::{self, Hash};
-use crate::ledger::Transaction;
+use crate::crypto::{self, Hash, PublicKey};
+use crate::ledger::{CausalLink, PolicyError, StateObject, Transaction};
+use crate::module::{self, Module, ModuleError};
 use crate::state_db::{StateDB, StateError};
+use crate::zvm::{self, ExecutionContext, VmError, GAS_PER_BYTE, ZVM};
 use serde::Serialize;
 use thiserror::Error;
 
@@ -13,18 +15,55 @@ pub enum ValidationError {
     InvalidSignature,
     #[error("An input State Object with ID {0:?} was not found")]
     InputNotFound(Hash),
+    #[error("State Object {0:?} is spent more than once by the transaction")]
+    DuplicateInput(Hash),
+    #[error("A reference input State Object with ID {0:?} was not found")]
+    ReferenceNotFound(Hash),
+    #[error("State Object {0:?} is both spent and referenced")]
+    ReferenceAlsoSpent(Hash),
     #[error("Transaction has no inputs and therefore no authority to act")]
     NoInputs,
-    #[error("Inputs are not all owned by the same public key")]
-    MultipleOwners,
+    #[error("Multisig owner {owner:?} needs {required} signatures, found {found}")]
+    InsufficientSignatures { owner: PublicKey, required: u8, found: usize },
+    #[error("Invalid multisig policy: {0}")]
+    InvalidPolicy(#[from] PolicyError),
+    #[error("Transaction has {inputs} inputs but {witnesses} witnesses")]
+    WitnessCountMismatch { inputs: usize, witnesses: usize },
+    #[error("The policy given for input {0:?} is not the one that owns it")]
+    PolicyMismatch(Hash),
+    #[error("The logic of output {id:?} failed verification: {reason}")]
+    InvalidLogic { id: Hash, reason: ModuleError },
+    #[error("Causal link refers to State Object {0:?}, which neither exists nor is created by the transaction")]
+    DanglingCausalLink(Hash),
+    #[error("The logic of input {0:?} refused to be spent")]
+    InputRejected(Hash),
+    #[error("The logic of State Object {0:?} rejected its causal link")]
+    CausalLinkRejected(Hash),
+    #[error("The logic of State Object {id:?} failed: {reason}")]
+    ScriptFailed { id: Hash, reason: VmError },
+    #[error("Transaction needs more than its gas limit of {limit}")]
+    OutOfGas { limit: u64 },
     #[error("Internal state database error: {0}")]
     StateError(#[from] StateError), // Allows automatic conversion from a StateError
 }
 
+// Gas charged for every transaction before any script runs.
+pub const TX_BASE_GAS: u64 = 1_000;
+// Gas charged for each State Object a transaction consumes, reads or creates,
+// on top of `GAS_PER_BYTE` for each byte of its data and logic.
+pub const GAS_PER_STATE_OBJECT: u64 = 100;
+// Gas charged for each signature a witness carries, times the keys it may be
+// checked against: one for a single-key owner, every policy key for a multisig.
+pub const GAS_PER_SIGNATURE_CHECK: u64 = 50;
+
 // The TransactionValidator holds a reference to the current state.
 // It uses this state to validate new transactions.
 pub struct TransactionValidator<'a> {
     state_db: &'a StateDB,
+    // The most gas a single transaction may use.
+    gas_limit: u64,
+    // The height of the block the transactions would be included in, as seen by scripts.
+    block_height: u64,
 }
 
 // This temporary struct is re-defined here to avoid making the one in `ledger.rs` public.
@@ -32,22 +71,56 @@ pub struct TransactionValidator<'a> {
 #[derive(Serialize)]
 struct HashableTransaction<'a> {
     inputs: &'a Vec<Hash>,
+    reference_inputs: &'a Vec<Hash>,
     outputs: &'a Vec<crate::ledger::StateObject>,
     causal_links: &'a Vec<crate::ledger::CausalLink>,
 }
 
 impl<'a> TransactionValidator<'a> {
     pub fn new(state_db: &'a StateDB) -> Self {
-        Self { state_db }
+        Self {
+            state_db,
+            gas_limit: zvm::DEFAULT_GAS_LIMIT,
+            block_height: 0,
+        }
+    }
+
+    /// Sets the most gas a single transaction may use.
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    /// Sets the block height scripts see: that of the block the transactions are for.
+    pub fn with_block_height(mut self, block_height: u64) -> Self {
+        self.block_height = block_height;
+        self
     }
 
     /// Validates a transaction against the current state.
-    /// This is the master function that performs all checks in order.
     pub fn validate_transaction(&self, tx: &Transaction) -> Result<(), ValidationError> {
+        self.validate_metered(tx).map(|_| ())
+    }
+
+    /// Validates a transaction and returns the gas it used, for charging fees.
+    /// This is the master function that performs all checks in order.
+    pub fn validate_metered(&self, tx: &Transaction) -> Result<u64, ValidationError> {
         self.check_id_hash(tx)?;
-        self.check_inputs_exist(tx)?;
-        self.check_signature(tx)?;
-        Ok(())
+        let inputs = self.check_inputs_exist(tx)?;
+        let references = self.check_reference_inputs(tx)?;
+        let gas_used = self.check_intrinsic_gas(tx, &inputs, &references)?;
+        self.check_output_logic(tx)?;
+        self.check_signature(tx, &inputs)?;
+
+        let context = ExecutionContext {
+            transaction: Some(tx),
+            inputs,
+            references,
+            block_height: self.block_height,
+            ..Default::default()
+        };
+        let gas_used = self.check_input_logic(&context, gas_used)?;
+        self.check_causal_links(tx, &context, gas_used)
     }
 
     /// Check 1: Verifies that the transaction's `id` field is the correct
@@ -55,6 +128,7 @@ impl<'a> TransactionValidator<'a> {
     fn check_id_hash(&self, tx: &Transaction) -> Result<(), ValidationError> {
         let hashable_part = HashableTransaction {
             inputs: &tx.inputs,
+            reference_inputs: &tx.reference_inputs,
             outputs: &tx.outputs,
             causal_links: &tx.causal_links,
         };
@@ -70,40 +144,187 @@ impl<'a> TransactionValidator<'a> {
     }
 
     /// Check 2: Ensures that every input State Object referenced by the transaction
-    /// actually exists in our current state database.
-    fn check_inputs_exist(&self, tx: &Transaction) -> Result<(), ValidationError> {
+    /// actually exists in our current state database, and is spent only once,
+    /// returning them in order.
+    fn check_inputs_exist(&self, tx: &Transaction) -> Result<Vec<&'a StateObject>, ValidationError> {
         if tx.inputs.is_empty() {
             return Err(ValidationError::NoInputs);
         }
-        for input_id in &tx.inputs {
-            self.state_db.get_so(input_id)?;
+        let mut inputs = Vec::with_capacity(tx.inputs.len());
+        for (i, input_id) in tx.inputs.iter().enumerate() {
+            if tx.inputs[..i].contains(input_id) {
+                return Err(ValidationError::DuplicateInput(*input_id));
+            }
+            inputs.push(self.state_db.get_so(input_id)?);
         }
-        Ok(())
+        Ok(inputs)
     }
 
-    /// Check 3: Verifies the cryptographic signature.
-    /// This proves that the rightful owner of the input assets authorized this transaction.
-    fn check_signature(&self, tx: &Transaction) -> Result<(), ValidationError> {
-        // Rule: A transaction must be signed by the owner of its inputs.
-        // We retrieve the public key of the owner of the *first* input.
-        let first_input_id = tx.inputs.get(0).ok_or(ValidationError::NoInputs)?;
-        let first_input_so = self.state_db.get_so(first_input_id)?;
-        let owner_pub_key = first_input_so.owner;
-
-        // Now, verify all other inputs are owned by the same key.
-        // This prevents creating a transaction that spends assets from multiple people.
-        for input_id in &tx.inputs[1..] {
-            let so = self.state_db.get_so(input_id)?;
-            if so.owner != owner_pub_key {
-                return Err(ValidationError::MultipleOwners);
+    /// Check 3: Ensures every reference input exists and isn't also being spent,
+    /// returning the referenced objects for the scripts to read.
+    fn check_reference_inputs(&self, tx: &Transaction) -> Result<Vec<&'a StateObject>, ValidationError> {
+        let mut references = Vec::with_capacity(tx.reference_inputs.len());
+        for id in &tx.reference_inputs {
+            if tx.inputs.contains(id) {
+                return Err(ValidationError::ReferenceAlsoSpent(*id));
             }
+            let so = self.state_db.get_so(id).map_err(|_| ValidationError::ReferenceNotFound(*id))?;
+            references.push(so);
+        }
+        Ok(references)
+    }
+
+    /// Check 4: Charges for the transaction's size, in State Objects and bytes,
+    /// and for the signature checks its witnesses call for, before any of them
+    /// runs. Returns the gas charged.
+    fn check_intrinsic_gas(
+        &self,
+        tx: &Transaction,
+        inputs: &[&StateObject],
+        references: &[&StateObject],
+    ) -> Result<u64, ValidationError> {
+        let mut gas = TX_BASE_GAS;
+        for so in inputs.iter().chain(references).copied().chain(&tx.outputs) {
+            gas = gas.saturating_add(object_gas(so));
+        }
+        for witness in &tx.witnesses {
+            let keys = witness.policy.as_ref().map_or(1, |policy| policy.keys.len()) as u64;
+            let checks = keys.saturating_mul(witness.signatures.len() as u64);
+            gas = gas.saturating_add(checks.saturating_mul(GAS_PER_SIGNATURE_CHECK));
         }
-        
-        // Verify the signature against the transaction's ID hash using the owner's public key.
-        if !crypto::verify_signature(&tx.signature, &tx.id, &owner_pub_key) {
-            return Err(ValidationError::InvalidSignature);
+        if gas > self.gas_limit {
+            return Err(ValidationError::OutOfGas { limit: self.gas_limit });
         }
+        Ok(gas)
+    }
 
+    /// Check 5: Runs the static verifier over the validation logic of every
+    /// output, so malformed code never lands on chain.
+    fn check_output_logic(&self, tx: &Transaction) -> Result<(), ValidationError> {
+        for output in tx.outputs.iter().filter(|so| !so.validation_logic.is_empty()) {
+            Module::from_logic(&output.validation_logic)
+                .and_then(|module| module.verify())
+                .map_err(|reason| ValidationError::InvalidLogic { id: output.id, reason })?;
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Check 6: Verifies the signatures.
+    /// This proves that the rightful owners of the input assets authorized this transaction.
+    fn check_signature(&self, tx: &Transaction, inputs: &[&StateObject]) -> Result<(), ValidationError> {
+        let owners: Vec<PublicKey> = inputs.iter().map(|so| so.owner).collect();
+        check_authorization(tx, &owners)
+    }
+
+    /// Check 7: Runs the validation logic of every input that has some, with the
+    /// whole transaction as its context. Each must halt with a non-zero value to
+    /// agree to being spent. Returns the total gas used so far.
+    fn check_input_logic(&self, context: &ExecutionContext<'_>, mut gas_used: u64) -> Result<u64, ValidationError> {
+        for input in &context.inputs {
+            let context = ExecutionContext {
+                current: Some(input),
+                ..context.clone()
+            };
+            let (result, gas) = self.run_script(input, context, module::entry::SPEND, gas_used)?;
+            if result == 0 {
+                return Err(ValidationError::InputRejected(input.id));
+            }
+            gas_used += gas;
+        }
+        Ok(gas_used)
+    }
+
+    /// Check 8: Follows every causal link. Both ends must exist, and the target's
+    /// validation logic is run with the source as its context, alongside the
+    /// transaction; a script that halts with zero, or fails, rejects the transaction.
+    fn check_causal_links(&self, tx: &Transaction, context: &ExecutionContext<'_>, mut gas_used: u64) -> Result<u64, ValidationError> {
+        for CausalLink { source_so_id, target_so_id } in &tx.causal_links {
+            let source = self.find_linked(tx, source_so_id)?;
+            let target = self.find_linked(tx, target_so_id)?;
+            let context = ExecutionContext {
+                current: Some(target),
+                link_source: Some(source),
+                ..context.clone()
+            };
+            let (result, gas) = self.run_script(target, context, module::entry::LINK, gas_used)?;
+            if result == 0 {
+                return Err(ValidationError::CausalLinkRejected(target.id));
+            }
+            gas_used += gas;
+        }
+        Ok(gas_used)
+    }
+
+    // Runs `so`'s validation logic from `entry_point` with whatever gas is left
+    // after `gas_used`, returning its result and the gas it used. An object
+    // without logic accepts anything for free.
+    fn run_script(
+        &self,
+        so: &StateObject,
+        context: ExecutionContext<'_>,
+        entry_point: usize,
+        gas_used: u64,
+    ) -> Result<(i64, u64), ValidationError> {
+        if so.validation_logic.is_empty() {
+            return Ok((1, 0));
+        }
+        let mut vm = ZVM::with_context(so.validation_logic.clone(), context, self.gas_limit - gas_used).with_entry_point(entry_point);
+        match vm.run() {
+            Ok(result) => Ok((result, vm.gas_used())),
+            Err(VmError::OutOfGas) => Err(ValidationError::OutOfGas { limit: self.gas_limit }),
+            Err(reason) => Err(ValidationError::ScriptFailed { id: so.id, reason }),
+        }
+    }
+
+    // Looks an end of a causal link up among the transaction's outputs, then in the state.
+    fn find_linked<'t>(&'t self, tx: &'t Transaction, id: &Hash) -> Result<&'t StateObject, ValidationError> {
+        if let Some(output) = tx.outputs.iter().find(|so| so.id == *id) {
+            return Ok(output);
+        }
+        self.state_db.get_so(id).map_err(|_| ValidationError::DanglingCausalLink(*id))
+    }
+}
+
+// The size-based gas for one State Object consumed, read or created.
+fn object_gas(so: &StateObject) -> u64 {
+    GAS_PER_STATE_OBJECT + GAS_PER_BYTE * (so.data.len() + so.validation_logic.len()) as u64
+}
+
+/// Checks that every input of `tx` is authorized by its witness. `owners` holds
+/// the owner of each input, in order. A single-key owner must have signed; a
+/// multisig owner needs its policy revealed and `threshold` of its keys signing.
+pub fn check_authorization(tx: &Transaction, owners: &[PublicKey]) -> Result<(), ValidationError> {
+    if tx.witnesses.len() != tx.inputs.len() {
+        return Err(ValidationError::WitnessCountMismatch {
+            inputs: tx.inputs.len(),
+            witnesses: tx.witnesses.len(),
+        });
+    }
+
+    for ((input_id, owner), witness) in tx.inputs.iter().zip(owners).zip(&tx.witnesses) {
+        let signed_by = |key: &PublicKey| witness.signatures.iter().any(|sig| crypto::verify_signature(sig, &tx.id, key));
+        match &witness.policy {
+            Some(policy) => {
+                policy.validate()?;
+                if policy.owner_key() != *owner {
+                    return Err(ValidationError::PolicyMismatch(*input_id));
+                }
+                // Policy keys are distinct, so this counts distinct signers.
+                let found = policy.keys.iter().filter(|key| signed_by(key)).count();
+                if found < policy.threshold as usize {
+                    return Err(ValidationError::InsufficientSignatures {
+                        owner: *owner,
+                        required: policy.threshold,
+                        found,
+                    });
+                }
+            }
+            None => {
+                if !signed_by(owner) {
+                    return Err(ValidationError::InvalidSignature);
+                }
+            }
+        }
+    }
+    Ok(())
+}
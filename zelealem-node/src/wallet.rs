@@ -0,0 +1,224 @@
+use crate::crypto::{self, Hash, KeyDerivationError, PublicKey};
+use crate::keystore::SecretKind;
+use crate::ledger::{StateObject, Transaction};
+use crate::p2p::ChainId;
+use crate::validator::{self, ValidationError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// The partial transaction format version written by this code.
+pub const PARTIAL_TX_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum WalletError {
+    #[error("Cannot transfer an amount of zero")]
+    ZeroAmount,
+    #[error("Insufficient funds: need {needed}, but only {available} is spendable")]
+    InsufficientFunds { needed: u64, available: u64 },
+    #[error("Transaction spends {0:?}, which is not one of this wallet's objects")]
+    UnknownInput(Hash),
+    #[error("This wallet holds a single imported key, so it has no account {0}")]
+    NoSuchAccount(u32),
+    #[error(transparent)]
+    KeyDerivation(#[from] KeyDerivationError),
+    #[error("The signing key cannot authorize any input of this transaction")]
+    CannotSign,
+    #[error("Partial transaction is malformed: {0}")]
+    MalformedPartial(String),
+    #[error("Unsupported partial transaction version {0}")]
+    UnsupportedPartialVersion(u32),
+    #[error("Partial transaction is for chain {found}, expected {expected}")]
+    WrongChain { expected: ChainId, found: ChainId },
+    #[error("Transaction is not fully signed: {0}")]
+    Incomplete(ValidationError),
+}
+
+/// Picks value objects worth at least `amount`, largest first, to keep the
+/// number of inputs (and so the transaction size) small.
+/// Returns the chosen objects and their total value.
+pub fn select_inputs(owned: &[StateObject], amount: u64) -> Result<(Vec<&StateObject>, u64), WalletError> {
+    let mut candidates: Vec<(&StateObject, u64)> = owned
+        .iter()
+        .filter_map(|so| so.amount().map(|a| (so, a)))
+        .collect();
+    // Sort by amount, then by id so the selection is deterministic.
+    candidates.sort_by(|(a, a_amt), (b, b_amt)| b_amt.cmp(a_amt).then(a.id.cmp(&b.id)));
+
+    let mut selected = Vec::new();
+    let mut total: u64 = 0;
+    for (so, so_amount) in &candidates {
+        if total >= amount {
+            break;
+        }
+        selected.push(*so);
+        total = total.saturating_add(*so_amount);
+    }
+
+    if total < amount {
+        let available = candidates.iter().fold(0u64, |acc, (_, a)| acc.saturating_add(*a));
+        return Err(WalletError::InsufficientFunds { needed: amount, available });
+    }
+    Ok((selected, total))
+}
+
+/// Builds an unsigned transaction paying `amount` to `recipient` out of `owned`.
+/// Any excess from the selected inputs comes back to `change_owner` as a change output.
+pub fn build_transfer(
+    owned: &[StateObject],
+    recipient: PublicKey,
+    amount: u64,
+    change_owner: PublicKey,
+) -> Result<Transaction, WalletError> {
+    if amount == 0 {
+        return Err(WalletError::ZeroAmount);
+    }
+    let (inputs, total) = select_inputs(owned, amount)?;
+
+    let mut outputs = vec![StateObject::new_salted_value(recipient, amount, crypto::random_salt())];
+    let change = total - amount;
+    if change > 0 {
+        outputs.push(StateObject::new_salted_value(change_owner, change, crypto::random_salt()));
+    }
+
+    Ok(Transaction::new(inputs.iter().map(|so| so.id).collect(), outputs, vec![]))
+}
+
+/// Signs `tx` with `secret_key`, after checking that it only spends objects the
+/// wallet knows it owns, so a wallet never signs away something it didn't intend to.
+pub fn sign_transaction(tx: &mut Transaction, owned: &[StateObject], secret_key: &[u8]) -> Result<(), WalletError> {
+    for input in &tx.inputs {
+        if !owned.iter().any(|so| &so.id == input) {
+            return Err(WalletError::UnknownInput(*input));
+        }
+    }
+    tx.sign(crypto::sign_data(&tx.id, secret_key));
+    Ok(())
+}
+
+/// The keypair of account `account`, given a wallet's decrypted secret.
+/// A seed wallet derives any number of accounts; a single imported key is account 0.
+pub fn account_keypair(kind: SecretKind, secret: &[u8], account: u32) -> Result<(PublicKey, Vec<u8>), WalletError> {
+    match kind {
+        SecretKind::Seed => Ok(crypto::derive_account_keypair(secret, account)?),
+        SecretKind::Key if account == 0 => Ok((crypto::public_key_from_secret(secret), secret.to_vec())),
+        SecretKind::Key => Err(WalletError::NoSuchAccount(account)),
+    }
+}
+
+// An unsigned (or not yet broadcast) transaction packaged with the State Objects
+// it spends, so that an offline machine with no access to the chain can review
+// exactly what it is signing. Nothing in it is trusted: `verify` recomputes every
+// id, so neither the transaction nor the inputs shown for review can be swapped.
+// Signatures accumulate as it is passed between signers, e.g. the members of a multisig.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartialTransaction {
+    pub version: u32,
+    pub chain_id: ChainId,
+    pub transaction: Transaction,
+    // The objects behind `transaction.inputs`, in the same order.
+    pub input_objects: Vec<StateObject>,
+}
+
+impl PartialTransaction {
+    pub fn new(transaction: Transaction, input_objects: Vec<StateObject>, chain_id: ChainId) -> Result<Self, WalletError> {
+        let partial = Self {
+            version: PARTIAL_TX_VERSION,
+            chain_id,
+            transaction,
+            input_objects,
+        };
+        partial.verify()?;
+        Ok(partial)
+    }
+
+    /// Checks that the transaction id matches its content and that the input
+    /// objects are exactly the ones the transaction spends.
+    pub fn verify(&self) -> Result<(), WalletError> {
+        if self.version != PARTIAL_TX_VERSION {
+            return Err(WalletError::UnsupportedPartialVersion(self.version));
+        }
+        let tx = &self.transaction;
+        let recomputed = Transaction::with_reference_inputs(
+            tx.inputs.clone(),
+            tx.reference_inputs.clone(),
+            tx.outputs.clone(),
+            tx.causal_links.clone(),
+        );
+        if recomputed.id != tx.id {
+            return Err(WalletError::MalformedPartial("transaction id does not match its content".into()));
+        }
+        if self.input_objects.len() != tx.inputs.len() {
+            return Err(WalletError::MalformedPartial("input objects do not match the inputs".into()));
+        }
+        if tx.witnesses.len() != tx.inputs.len() {
+            return Err(WalletError::MalformedPartial("there must be one witness per input".into()));
+        }
+        for (so, input) in self.input_objects.iter().zip(&tx.inputs) {
+            let recomputed = StateObject::new(so.owner, so.data.clone(), so.validation_logic.clone());
+            if recomputed.id != so.id || so.id != *input {
+                return Err(WalletError::MalformedPartial(format!("input object {:?} does not match", input)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Total value of the inputs and outputs that are plain value objects.
+    pub fn value_in_out(&self) -> (u64, u64) {
+        let sum = |objects: &[StateObject]| objects.iter().filter_map(StateObject::amount).fold(0u64, u64::saturating_add);
+        (sum(&self.input_objects), sum(&self.transaction.outputs))
+    }
+
+    /// Signs every input `secret_key` may authorize: those it owns, and those owned by a
+    /// multisig policy it is a member of. Other parties' inputs are left for them to sign,
+    /// so a multi-party transaction collects its signatures as it is passed around.
+    /// Returns the indexes of the inputs signed.
+    pub fn sign(&mut self, secret_key: &[u8]) -> Result<Vec<usize>, WalletError> {
+        self.verify()?;
+        let signer = crypto::public_key_from_secret(secret_key);
+        let signature = crypto::sign_data(&self.transaction.id, secret_key);
+
+        let mut signed = Vec::new();
+        for (index, so) in self.input_objects.iter().enumerate() {
+            let witness = &mut self.transaction.witnesses[index];
+            let may_sign = match &witness.policy {
+                Some(policy) => policy.owner_key() == so.owner && policy.keys.contains(&signer),
+                None => so.owner == signer,
+            };
+            if may_sign {
+                if !witness.signatures.contains(&signature) {
+                    witness.signatures.push(signature);
+                }
+                signed.push(index);
+            }
+        }
+        if signed.is_empty() {
+            return Err(WalletError::CannotSign);
+        }
+        Ok(signed)
+    }
+
+    /// Returns the transaction, ready to submit, once every owner has signed.
+    pub fn finalize(self, chain_id: ChainId) -> Result<Transaction, WalletError> {
+        self.verify()?;
+        if self.chain_id != chain_id {
+            return Err(WalletError::WrongChain { expected: chain_id, found: self.chain_id });
+        }
+        let owners: Vec<PublicKey> = self.input_objects.iter().map(|so| so.owner).collect();
+        validator::check_authorization(&self.transaction, &owners).map_err(WalletError::Incomplete)?;
+        Ok(self.transaction)
+    }
+
+    /// Encodes the partial transaction as hex, for moving between machines as text.
+    pub fn encode(&self) -> String {
+        hex::encode(bincode::serde::encode_to_vec(self, bincode::config::standard()).expect("Failed to serialize partial TX"))
+    }
+
+    /// Decodes and verifies a partial transaction produced by `encode`.
+    pub fn decode(text: &str) -> Result<Self, WalletError> {
+        let bytes = hex::decode(text.trim()).map_err(|e| WalletError::MalformedPartial(e.to_string()))?;
+        let (partial, _): (Self, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .map_err(|e| WalletError::MalformedPartial(e.to_string()))?;
+        partial.verify()?;
+        Ok(partial)
+    }
+}
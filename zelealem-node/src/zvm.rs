@@ -1,62 +1,615 @@
-use crate::bytecode::OpCode;
+use crate::bytecode::{self, collection, OpCode};
+use crate::crypto::{self, Hash, PublicKey, Signature};
+use crate::ledger::{StateObject, Transaction};
+use crate::module::{self, Module, ModuleError};
+use thiserror::Error;
+
+// Gas charged per byte of bytecode loaded and of State Object data read, so
+// costs grow with the size of the objects a script touches.
+pub const GAS_PER_BYTE: u64 = 1;
+// The deepest Calls may nest.
+pub const MAX_CALL_DEPTH: usize = 64;
+// The number of local variable slots in each call frame.
+pub const MAX_LOCALS: usize = 16;
+// The stack depth limit used unless `ZVM::with_max_stack` sets another.
+pub const DEFAULT_MAX_STACK: usize = 1024;
+// The gas limit used by `ZVM::new`.
+pub const DEFAULT_GAS_LIMIT: u64 = 1_000_000;
+// The longest byte array a script may hold.
+pub const MAX_BYTES_LEN: usize = 1024;
+
+// A value on the stack or in a local: an integer, or a bounded byte array
+// such as an id, a public key or a slice of State Object data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Int(0)
+    }
+}
+
+impl Value {
+    // The gas for copying this value: byte arrays cost by size.
+    fn copy_gas(&self) -> u64 {
+        match self {
+            Value::Int(_) => 0,
+            Value::Bytes(bytes) => GAS_PER_BYTE * bytes.len() as u64,
+        }
+    }
+}
+
+// Every way a script can fail. Bytecode arrives from the network, so malformed
+// code must end up here rather than panic the node.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    #[error("Invalid opcode 0x{0:02x}")]
+    InvalidOpcode(u8),
+    #[error("{0:?} needs more values than the stack holds")]
+    StackUnderflow(OpCode),
+    #[error("{0:?} at the end of the code is missing its immediate")]
+    TruncatedImmediate(OpCode),
+    #[error("Execution ran past the end of the code without halting")]
+    PcOutOfBounds,
+    #[error("Out of gas")]
+    OutOfGas,
+    #[error("{0:?} overflowed")]
+    Overflow(OpCode),
+    #[error("{0:?} divided by zero")]
+    DivisionByZero(OpCode),
+    #[error("The jump at offset {at} targets {target}, which is not the start of an instruction")]
+    InvalidJumpTarget { at: usize, target: usize },
+    #[error("Calls nested deeper than {MAX_CALL_DEPTH}")]
+    CallDepthExceeded,
+    #[error("Return without a matching Call")]
+    ReturnWithoutCall,
+    #[error("The stack grew past its limit of {0} values")]
+    StackOverflow(usize),
+    #[error("Local slot {0} is out of range (frames hold {MAX_LOCALS})")]
+    InvalidLocal(u8),
+    #[error("LinkSource requires a causal link source")]
+    NoLinkSource,
+    #[error("No reference input at index {0}")]
+    NoSuchReference(i64),
+    #[error("{0:?} has no collection {1}")]
+    InvalidCollection(OpCode, u8),
+    #[error("No object at index {index} of collection {collection}")]
+    NoSuchObject { collection: u8, index: i64 },
+    #[error("No causal link at index {0}")]
+    NoSuchLink(i64),
+    #[error("{0:?} read at negative offset")]
+    NegativeOffset(OpCode),
+    #[error("{0:?} needs the transaction in its context")]
+    NoTransaction(OpCode),
+    #[error("{0:?} was given a value of the wrong type")]
+    TypeMismatch(OpCode),
+    #[error("A byte array of {0} bytes exceeds the limit of {MAX_BYTES_LEN}")]
+    BytesTooLong(usize),
+    #[error("{0:?} reached outside its byte array")]
+    OutOfRange(OpCode),
+    #[error("Invalid module: {0}")]
+    InvalidModule(Box<ModuleError>),
+    #[error("No constant at index {0}")]
+    NoSuchConstant(u8),
+    #[error("{opcode:?} expected {expected} bytes, found {found}")]
+    WrongLength { opcode: OpCode, expected: usize, found: usize },
+}
+
+// What a running script can see of the transaction that triggered it.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionContext<'a> {
+    /// The transaction being validated.
+    pub transaction: Option<&'a Transaction>,
+    /// The objects the transaction spends, in the order of its inputs.
+    pub inputs: Vec<&'a StateObject>,
+    /// The object whose logic is running: an input being spent, or a causal link's target.
+    pub current: Option<&'a StateObject>,
+    /// The source of the causal link being followed, when the script runs as the link's target.
+    pub link_source: Option<&'a StateObject>,
+    /// The transaction's reference inputs, in order.
+    pub references: Vec<&'a StateObject>,
+    /// The height of the block the transaction is being validated for.
+    pub block_height: u64,
+}
 
 // The Zelealem Virtual Machine
-pub struct ZVM {
-    /// The smart contract code to execute.
+pub struct ZVM<'a> {
+    /// The validation logic to run; once running, the code section of its module.
     bytecode: Vec<u8>,
-    /// The execution stack. We will start by only allowing 64-bit signed integers.
-    stack: Vec<i64>,
+    /// The module's constant pool.
+    constants: Vec<Vec<u8>>,
+    /// Which of the module's entry points to start at; see `module::entry`.
+    entry_point: usize,
+    /// The execution stack of integers and byte arrays.
+    stack: Vec<Value>,
     /// The Program Counter, pointing to the next instruction to be executed.
     pc: usize,
+    /// The most values the stack may hold.
+    max_stack: usize,
+    /// The current call frame's local variables; each Call starts with fresh, zeroed slots.
+    locals: Vec<Value>,
+    /// For each Call in progress, where to return to and the caller's locals.
+    call_stack: Vec<(usize, Vec<Value>)>,
+    context: ExecutionContext<'a>,
+    /// The most gas this run may use, and how much it has used so far.
+    gas_limit: u64,
+    gas_used: u64,
 }
 
-impl ZVM {
+impl<'a> ZVM<'a> {
     /// Creates a new ZVM instance ready to run the given bytecode.
     pub fn new(bytecode: Vec<u8>) -> Self {
+        Self::with_context(bytecode, ExecutionContext::default(), DEFAULT_GAS_LIMIT)
+    }
+
+    /// Creates a ZVM that runs `bytecode` with `context` available to it,
+    /// failing with `VmError::OutOfGas` once it would use more than `gas_limit`.
+    pub fn with_context(bytecode: Vec<u8>, context: ExecutionContext<'a>, gas_limit: u64) -> Self {
         Self {
             bytecode,
+            constants: Vec::new(),
+            entry_point: module::entry::SPEND,
             stack: Vec::new(),
             pc: 0,
+            max_stack: DEFAULT_MAX_STACK,
+            locals: vec![Value::default(); MAX_LOCALS],
+            call_stack: Vec::new(),
+            context,
+            gas_limit,
+            gas_used: 0,
+        }
+    }
+
+    /// Sets the most values the stack may hold.
+    pub fn with_max_stack(mut self, max_stack: usize) -> Self {
+        self.max_stack = max_stack;
+        self
+    }
+
+    /// Sets which of the module's entry points to start at. A module without
+    /// that entry point starts at its first.
+    pub fn with_entry_point(mut self, entry_point: usize) -> Self {
+        self.entry_point = entry_point;
+        self
+    }
+
+    /// The gas used so far. After running out of gas this is the whole limit.
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    // Charges `amount` gas, failing if that would exceed the limit.
+    fn charge(&mut self, amount: u64) -> Result<(), VmError> {
+        match self.gas_used.checked_add(amount) {
+            Some(total) if total <= self.gas_limit => {
+                self.gas_used = total;
+                Ok(())
+            }
+            _ => {
+                self.gas_used = self.gas_limit;
+                Err(VmError::OutOfGas)
+            }
         }
     }
 
+    // Pushes a value, failing if the stack is full.
+    fn push_value(&mut self, value: Value) -> Result<(), VmError> {
+        if self.stack.len() >= self.max_stack {
+            return Err(VmError::StackOverflow(self.max_stack));
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn push(&mut self, value: i64) -> Result<(), VmError> {
+        self.push_value(Value::Int(value))
+    }
+
+    // Pushes a new byte array, charging for its size.
+    fn push_bytes(&mut self, bytes: Vec<u8>) -> Result<(), VmError> {
+        if bytes.len() > MAX_BYTES_LEN {
+            return Err(VmError::BytesTooLong(bytes.len()));
+        }
+        self.charge(GAS_PER_BYTE * bytes.len() as u64)?;
+        self.push_value(Value::Bytes(bytes))
+    }
+
+    // Pops the top of the stack on behalf of `opcode`.
+    fn pop_value(&mut self, opcode: OpCode) -> Result<Value, VmError> {
+        self.stack.pop().ok_or(VmError::StackUnderflow(opcode))
+    }
+
+    // Pops an integer on behalf of `opcode`.
+    fn pop(&mut self, opcode: OpCode) -> Result<i64, VmError> {
+        match self.pop_value(opcode)? {
+            Value::Int(value) => Ok(value),
+            Value::Bytes(_) => Err(VmError::TypeMismatch(opcode)),
+        }
+    }
+
+    // Pops a byte array on behalf of `opcode`.
+    fn pop_bytes(&mut self, opcode: OpCode) -> Result<Vec<u8>, VmError> {
+        match self.pop_value(opcode)? {
+            Value::Bytes(bytes) => Ok(bytes),
+            Value::Int(_) => Err(VmError::TypeMismatch(opcode)),
+        }
+    }
+
+    // Pops two values of the same type and pushes 1 if `a` and `b` are ordered as `want`
+    // says, 0 otherwise. Integers compare numerically, byte arrays lexicographically.
+    fn compare(&mut self, opcode: OpCode, want: std::cmp::Ordering) -> Result<(), VmError> {
+        let b = self.pop_value(opcode)?;
+        let a = self.pop_value(opcode)?;
+        let ordering = match (&a, &b) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            _ => return Err(VmError::TypeMismatch(opcode)),
+        };
+        self.push((ordering == want) as i64)
+    }
+
+    // Pops `b` then `a` and pushes `op(a, b)`; `None` means the result overflowed.
+    fn binary(&mut self, opcode: OpCode, op: impl FnOnce(i64, i64) -> Option<i64>) -> Result<(), VmError> {
+        let b = self.pop(opcode)?;
+        let a = self.pop(opcode)?;
+        let result = op(a, b).ok_or(VmError::Overflow(opcode))?;
+        self.push(result)
+    }
+
+    // Pops a value and pushes `op` of it.
+    fn unary(&mut self, opcode: OpCode, op: impl FnOnce(i64) -> i64) -> Result<(), VmError> {
+        let a = self.pop(opcode)?;
+        self.push(op(a))
+    }
+
+    // Reads the 8-byte immediate following `opcode` and advances past it.
+    fn read_immediate(&mut self, opcode: OpCode) -> Result<i64, VmError> {
+        let bytes: [u8; 8] = self
+            .bytecode
+            .get(self.pc..self.pc + 8)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(VmError::TruncatedImmediate(opcode))?;
+        self.pc += 8;
+        Ok(i64::from_le_bytes(bytes))
+    }
+
+    // Reads the one-byte immediate following `opcode` and advances past it.
+    fn read_byte(&mut self, opcode: OpCode) -> Result<u8, VmError> {
+        let byte = *self.bytecode.get(self.pc).ok_or(VmError::TruncatedImmediate(opcode))?;
+        self.pc += 1;
+        Ok(byte)
+    }
+
+    // Reads the local slot following `opcode` and advances past it.
+    fn read_local(&mut self, opcode: OpCode) -> Result<usize, VmError> {
+        let slot = self.read_byte(opcode)?;
+        if slot as usize >= MAX_LOCALS {
+            return Err(VmError::InvalidLocal(slot));
+        }
+        Ok(slot as usize)
+    }
+
+    // Pops a byte offset for a read, which must not be negative.
+    fn pop_offset(&mut self, opcode: OpCode) -> Result<usize, VmError> {
+        let offset = self.pop(opcode)?;
+        usize::try_from(offset).map_err(|_| VmError::NegativeOffset(opcode))
+    }
+
+    // Pops a byte array that must be exactly `N` bytes long, such as a hash or key.
+    fn pop_array<const N: usize>(&mut self, opcode: OpCode) -> Result<[u8; N], VmError> {
+        let bytes = self.pop_bytes(opcode)?;
+        let found = bytes.len();
+        bytes.try_into().map_err(|_| VmError::WrongLength { opcode, expected: N, found })
+    }
+
+    fn transaction(&self, opcode: OpCode) -> Result<&'a Transaction, VmError> {
+        self.context.transaction.ok_or(VmError::NoTransaction(opcode))
+    }
+
+    // Finds object `index` of the collection named `which`.
+    fn object(&self, opcode: OpCode, which: u8, index: i64) -> Result<&'a StateObject, VmError> {
+        let missing = VmError::NoSuchObject { collection: which, index };
+        let Ok(i) = usize::try_from(index) else {
+            return Err(missing);
+        };
+        let found = match which {
+            collection::INPUTS => self.context.inputs.get(i).copied(),
+            collection::OUTPUTS => self.transaction(opcode)?.outputs.get(i),
+            collection::REFERENCES => self.context.references.get(i).copied(),
+            collection::CURRENT => self.context.current.filter(|_| i == 0),
+            _ => return Err(VmError::InvalidCollection(opcode, which)),
+        };
+        found.ok_or(missing)
+    }
+
+    // Reads the jump target following `opcode` and advances past it.
+    // The load-time analysis has already checked it is a valid destination.
+    fn read_target(&mut self, opcode: OpCode) -> Result<usize, VmError> {
+        let target = bytecode::jump_target(&self.bytecode, self.pc - 1).ok_or(VmError::TruncatedImmediate(opcode))?;
+        self.pc += bytecode::JUMP_TARGET_LEN;
+        Ok(target)
+    }
+
+    /// Unpacks the validation logic into its code and constants, and moves to the entry point.
+    fn load(&mut self) -> Result<(), VmError> {
+        let invalid = |error| VmError::InvalidModule(Box::new(error));
+        let module = Module::from_logic(&self.bytecode).map_err(invalid)?;
+        bytecode::check_jump_targets(&module.code)?;
+        let entry = *module.entry_points.get(self.entry_point).or(module.entry_points.first()).unwrap_or(&0);
+        let starts_instruction = bytecode::decode_instructions(&module.code)?.iter().any(|(offset, _)| *offset == entry as usize);
+        if !starts_instruction && !module.code.is_empty() {
+            return Err(invalid(ModuleError::InvalidEntryPoint(entry)));
+        }
+        self.max_stack = self.max_stack.min(module.max_stack as usize);
+        self.pc = entry as usize;
+        self.bytecode = module.code;
+        self.constants = module.constants;
+        Ok(())
+    }
+
     /// The main execution loop of the ZVM.
     /// This is the "Fetch-Decode-Execute" cycle.
-    pub fn run(&mut self) -> Result<i64, String> {
+    pub fn run(&mut self) -> Result<i64, VmError> {
+        // Loading the code is paid for up front.
+        self.charge(GAS_PER_BYTE * self.bytecode.len() as u64)?;
+        self.load()?;
         loop {
             // 1. Fetch
-            let opcode_byte = self.bytecode.get(self.pc).cloned().ok_or("PC out of bounds")?;
+            let opcode_byte = *self.bytecode.get(self.pc).ok_or(VmError::PcOutOfBounds)?;
             self.pc += 1;
 
             // 2. Decode
-            let opcode = OpCode::from(opcode_byte);
+            let opcode = OpCode::try_from(opcode_byte)?;
+            self.charge(opcode.gas_cost())?;
 
             // 3. Execute
             match opcode {
                 OpCode::Halt => {
                     // Stop execution and return the top value of the stack.
-                    return self.stack.pop().ok_or("Execution halted on empty stack".to_string());
+                    return self.pop(opcode);
                 }
                 OpCode::Push => {
                     // The PUSH opcode is followed by 8 bytes representing the i64 value.
-                    let value_bytes: [u8; 8] = self.bytecode[self.pc..self.pc + 8]
-                        .try_into()
-                        .map_err(|e| format!("Failed to read push argument: {}", e))?;
-                    let value = i64::from_le_bytes(value_bytes);
-                    self.stack.push(value);
-                    self.pc += 8; // Advance PC past the 8-byte argument.
-                }
-                OpCode::Add => {
-                    let b = self.stack.pop().ok_or("ADD requires two values on the stack")?;
-                    let a = self.stack.pop().ok_or("ADD requires two values on the stack")?;
-                    self.stack.push(a + b);
-                }
-                OpCode::Sub => {
-                    let b = self.stack.pop().ok_or("SUB requires two values on the stack")?;
-                    let a = self.stack.pop().ok_or("SUB requires two values on the stack")?;
-                    self.stack.push(a - b);
+                    let value = self.read_immediate(opcode)?;
+                    self.push(value)?;
+                }
+                // Arithmetic is checked: a result that doesn't fit fails the script rather than wrapping.
+                OpCode::Add => self.binary(opcode, i64::checked_add)?,
+                OpCode::Sub => self.binary(opcode, i64::checked_sub)?,
+                OpCode::Mul => self.binary(opcode, i64::checked_mul)?,
+                OpCode::Div | OpCode::Mod => {
+                    let b = self.pop(opcode)?;
+                    let a = self.pop(opcode)?;
+                    if b == 0 {
+                        return Err(VmError::DivisionByZero(opcode));
+                    }
+                    // Only i64::MIN / -1 overflows.
+                    let result = if opcode == OpCode::Div { a.checked_div(b) } else { a.checked_rem(b) };
+                    self.push(result.ok_or(VmError::Overflow(opcode))?)?;
+                }
+                OpCode::Eq => self.compare(opcode, std::cmp::Ordering::Equal)?,
+                OpCode::Lt => self.compare(opcode, std::cmp::Ordering::Less)?,
+                OpCode::Gt => self.compare(opcode, std::cmp::Ordering::Greater)?,
+                OpCode::And => self.binary(opcode, |a, b| Some((a != 0 && b != 0) as i64))?,
+                OpCode::Or => self.binary(opcode, |a, b| Some((a != 0 || b != 0) as i64))?,
+                OpCode::Not => self.unary(opcode, |a| (a == 0) as i64)?,
+                OpCode::BitAnd => self.binary(opcode, |a, b| Some(a & b))?,
+                OpCode::BitOr => self.binary(opcode, |a, b| Some(a | b))?,
+                OpCode::BitXor => self.binary(opcode, |a, b| Some(a ^ b))?,
+                OpCode::BitNot => self.unary(opcode, |a| !a)?,
+                // A left shift that drops set bits or flips the sign overflows, like Mul would.
+                OpCode::Shl => self.binary(opcode, |a, b| {
+                    let shifted = a.checked_shl(u32::try_from(b).ok()?)?;
+                    (shifted >> b == a).then_some(shifted)
+                })?,
+                OpCode::Shr => self.binary(opcode, |a, b| a.checked_shr(u32::try_from(b).ok()?))?,
+                OpCode::Jump => self.pc = self.read_target(opcode)?,
+                OpCode::JumpIf => {
+                    let target = self.read_target(opcode)?;
+                    if self.pop(opcode)? != 0 {
+                        self.pc = target;
+                    }
+                }
+                OpCode::Call => {
+                    let target = self.read_target(opcode)?;
+                    if self.call_stack.len() == MAX_CALL_DEPTH {
+                        return Err(VmError::CallDepthExceeded);
+                    }
+                    let caller_locals = std::mem::replace(&mut self.locals, vec![Value::default(); MAX_LOCALS]);
+                    self.call_stack.push((self.pc, caller_locals));
+                    self.pc = target;
+                }
+                OpCode::Return => {
+                    (self.pc, self.locals) = self.call_stack.pop().ok_or(VmError::ReturnWithoutCall)?;
+                }
+                OpCode::Dup => {
+                    let a = self.stack.last().ok_or(VmError::StackUnderflow(opcode))?.clone();
+                    self.charge(a.copy_gas())?;
+                    self.push_value(a)?;
+                }
+                OpCode::Swap => {
+                    let len = self.stack.len();
+                    if len < 2 {
+                        return Err(VmError::StackUnderflow(opcode));
+                    }
+                    self.stack.swap(len - 1, len - 2);
+                }
+                OpCode::Pop => {
+                    self.pop_value(opcode)?;
+                }
+                OpCode::LoadLocal => {
+                    let slot = self.read_local(opcode)?;
+                    let value = self.locals[slot].clone();
+                    self.charge(value.copy_gas())?;
+                    self.push_value(value)?;
+                }
+                OpCode::StoreLocal => {
+                    let slot = self.read_local(opcode)?;
+                    self.locals[slot] = self.pop_value(opcode)?;
+                }
+                OpCode::LinkSource => {
+                    let source = self.context.link_source.ok_or(VmError::NoLinkSource)?;
+                    self.charge(GAS_PER_BYTE * source.data.len() as u64)?;
+                    self.push(word_at(&source.data, 0))?;
+                }
+                OpCode::Reference => {
+                    let index = self.pop(opcode)?;
+                    let reference = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| self.context.references.get(i).copied())
+                        .ok_or(VmError::NoSuchReference(index))?;
+                    self.charge(GAS_PER_BYTE * reference.data.len() as u64)?;
+                    self.push(word_at(&reference.data, 0))?;
+                }
+                OpCode::Count => {
+                    let which = self.read_byte(opcode)?;
+                    let count = match which {
+                        collection::INPUTS => self.context.inputs.len(),
+                        collection::OUTPUTS => self.transaction(opcode)?.outputs.len(),
+                        collection::REFERENCES => self.context.references.len(),
+                        collection::CURRENT => self.context.current.is_some() as usize,
+                        collection::LINKS => self.transaction(opcode)?.causal_links.len(),
+                        _ => return Err(VmError::InvalidCollection(opcode, which)),
+                    };
+                    self.push(count as i64)?;
+                }
+                OpCode::DataLen => {
+                    let which = self.read_byte(opcode)?;
+                    let index = self.pop(opcode)?;
+                    let so = self.object(opcode, which, index)?;
+                    self.push(so.data.len() as i64)?;
+                }
+                OpCode::DataWord | OpCode::OwnerWord | OpCode::IdWord => {
+                    let which = self.read_byte(opcode)?;
+                    let offset = self.pop_offset(opcode)?;
+                    let index = self.pop(opcode)?;
+                    let so = self.object(opcode, which, index)?;
+                    let bytes: &[u8] = match opcode {
+                        OpCode::DataWord => &so.data,
+                        OpCode::OwnerWord => &so.owner.0,
+                        _ => &so.id,
+                    };
+                    self.push(word_at(bytes, offset))?;
+                }
+                OpCode::SelfIndex => {
+                    let position = self
+                        .context
+                        .current
+                        .and_then(|current| self.context.inputs.iter().position(|input| input.id == current.id));
+                    self.push(position.map_or(-1, |i| i as i64))?;
+                }
+                OpCode::BlockHeight => self.push(self.context.block_height as i64)?,
+                OpCode::LinkWord => {
+                    let end = self.read_byte(opcode)?;
+                    let offset = self.pop_offset(opcode)?;
+                    let index = self.pop(opcode)?;
+                    let link = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| self.transaction(opcode).ok()?.causal_links.get(i))
+                        .ok_or(VmError::NoSuchLink(index))?;
+                    let id = match end {
+                        0 => link.source_so_id,
+                        1 => link.target_so_id,
+                        _ => return Err(VmError::InvalidCollection(opcode, end)),
+                    };
+                    self.push(word_at(&id, offset))?;
+                }
+                OpCode::PushBytes => {
+                    let len = self.read_byte(opcode)? as usize;
+                    let bytes = self
+                        .bytecode
+                        .get(self.pc..self.pc + len)
+                        .ok_or(VmError::TruncatedImmediate(opcode))?
+                        .to_vec();
+                    self.pc += len;
+                    self.push_bytes(bytes)?;
+                }
+                OpCode::Concat => {
+                    let b = self.pop_bytes(opcode)?;
+                    let mut a = self.pop_bytes(opcode)?;
+                    a.extend_from_slice(&b);
+                    self.push_bytes(a)?;
+                }
+                OpCode::Slice => {
+                    let len = self.pop_offset(opcode)?;
+                    let start = self.pop_offset(opcode)?;
+                    let bytes = self.pop_bytes(opcode)?;
+                    let slice = start
+                        .checked_add(len)
+                        .and_then(|end| bytes.get(start..end))
+                        .ok_or(VmError::OutOfRange(opcode))?;
+                    self.push_bytes(slice.to_vec())?;
+                }
+                OpCode::Len => {
+                    let bytes = self.pop_bytes(opcode)?;
+                    self.push(bytes.len() as i64)?;
+                }
+                OpCode::IntToBytes => {
+                    let value = self.pop(opcode)?;
+                    self.push_bytes(value.to_le_bytes().to_vec())?;
+                }
+                OpCode::BytesToInt => {
+                    let bytes = self.pop_bytes(opcode)?;
+                    if bytes.len() > 8 {
+                        return Err(VmError::OutOfRange(opcode));
+                    }
+                    self.push(word_at(&bytes, 0))?;
+                }
+                OpCode::Data | OpCode::Owner | OpCode::Id => {
+                    let which = self.read_byte(opcode)?;
+                    let index = self.pop(opcode)?;
+                    let so = self.object(opcode, which, index)?;
+                    let bytes = match opcode {
+                        OpCode::Data => so.data.clone(),
+                        OpCode::Owner => so.owner.0.to_vec(),
+                        _ => so.id.to_vec(),
+                    };
+                    self.push_bytes(bytes)?;
+                }
+                OpCode::PushConst => {
+                    let index = self.read_byte(opcode)?;
+                    let constant = self.constants.get(index as usize).ok_or(VmError::NoSuchConstant(index))?.clone();
+                    self.push_bytes(constant)?;
+                }
+                OpCode::Sha256 => {
+                    let bytes = self.pop_bytes(opcode)?;
+                    self.push_bytes(crypto::hash_data(&bytes).to_vec())?;
+                }
+                OpCode::VerifySignature => {
+                    let key = PublicKey(self.pop_array(opcode)?);
+                    let signature: Signature = self.pop_array(opcode)?;
+                    let tx = self.transaction(opcode)?;
+                    self.push(crypto::verify_signature(&signature, &tx.id, &key) as i64)?;
+                }
+                OpCode::MerkleVerify => {
+                    let root: Hash = self.pop_array(opcode)?;
+                    let index = self.pop(opcode)?;
+                    let proof = self.pop_bytes(opcode)?;
+                    let leaf: Hash = self.pop_array(opcode)?;
+                    if proof.len() % 32 != 0 {
+                        return Err(VmError::WrongLength { opcode, expected: proof.len() / 32 * 32, found: proof.len() });
+                    }
+                    let siblings: Vec<Hash> = proof.chunks_exact(32).map(|chunk| chunk.try_into().unwrap()).collect();
+                    // An index beyond the tree's width can't be the position of the leaf.
+                    let valid = u64::try_from(index).is_ok_and(|index| {
+                        index.checked_shr(siblings.len() as u32).unwrap_or(0) == 0
+                            && crypto::merkle_root_from_proof(&leaf, index, &siblings) == root
+                    });
+                    self.push(valid as i64)?;
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// The 8 bytes of `bytes` starting at `offset` as a little-endian i64, zero-padded past the end.
+fn word_at(bytes: &[u8], offset: usize) -> i64 {
+    let mut word = [0u8; 8];
+    let available = bytes.get(offset..).unwrap_or_default();
+    let len = available.len().min(8);
+    word[..len].copy_from_slice(&available[..len]);
+    i64::from_le_bytes(word)
+}
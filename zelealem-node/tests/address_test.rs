@@ -0,0 +1,61 @@
+use zelealem_node::{
+    address::{self, AddressError},
+    crypto::PublicKey,
+    p2p::DEFAULT_CHAIN_ID,
+};
+
+#[test]
+fn test_address_round_trip() {
+    let key = PublicKey([0x5a; 32]);
+
+    let address = key.to_string();
+    assert!(address.starts_with("zel1"));
+    assert_eq!(address, key.to_address(DEFAULT_CHAIN_ID));
+    assert_eq!(address.parse::<PublicKey>().unwrap(), key);
+
+    // Upper-case addresses (e.g. from QR codes) decode too.
+    assert_eq!(address.to_uppercase().parse::<PublicKey>().unwrap(), key);
+
+    let testnet = key.to_address(42);
+    assert!(testnet.starts_with("zel421"));
+    assert_eq!(PublicKey::from_address(&testnet, 42).unwrap(), key);
+}
+
+#[test]
+fn test_address_encoding_is_stable() {
+    // Pinned so the address of a key never silently changes.
+    assert_eq!(
+        PublicKey([0; 32]).to_string(),
+        "zel1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqfn0all"
+    );
+}
+
+#[test]
+fn test_mistyped_and_foreign_addresses_are_rejected() {
+    let key = PublicKey([0x5a; 32]);
+    let address = key.to_string();
+
+    // Any single substituted character breaks the checksum.
+    for i in 4..address.len() {
+        let mut typo = address.clone().into_bytes();
+        typo[i] = if typo[i] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert!(matches!(typo.parse::<PublicKey>(), Err(AddressError::Invalid(_))), "{}", typo);
+    }
+
+    assert_eq!(
+        PublicKey::from_address(&address, 42),
+        Err(AddressError::WrongNetwork { expected: "zel42".to_string(), found: "zel".to_string() })
+    );
+    assert!(matches!(key.to_address(42).parse::<PublicKey>(), Err(AddressError::WrongNetwork { .. })));
+
+    // Plain Bech32 (rather than Bech32m) checksums and wrong payload lengths are refused.
+    let hrp = bech32::Hrp::parse("zel").unwrap();
+    let bech32 = bech32::encode::<bech32::Bech32>(hrp, &key.0).unwrap();
+    assert!(matches!(bech32.parse::<PublicKey>(), Err(AddressError::Invalid(_))));
+    let short = bech32::encode::<bech32::Bech32m>(hrp, &[1; 20]).unwrap();
+    assert_eq!(short.parse::<PublicKey>(), Err(AddressError::WrongLength(20)));
+
+    assert!(hex::encode(key.0).parse::<PublicKey>().is_err());
+    assert_eq!(address::hrp(DEFAULT_CHAIN_ID), "zel");
+}
@@ -0,0 +1,118 @@
+use zelealem_node::{
+    asm::{self, AsmError},
+    bytecode::{collection, OpCode},
+    module::{Module, MAGIC},
+    zvm::ZVM,
+};
+
+#[test]
+fn test_assemble_bare_code() {
+    let source = "
+        ; Counts down from 3, leaving 0.
+            push 3
+        loop:
+            push 1
+            sub             ; n - 1
+            dup
+            jump_if loop
+            push_bytes \"ok\"
+            len
+            add
+            halt
+    ";
+    let mut expected = vec![OpCode::Push as u8];
+    expected.extend_from_slice(&3i64.to_le_bytes());
+    expected.push(OpCode::Push as u8);
+    expected.extend_from_slice(&1i64.to_le_bytes());
+    expected.extend_from_slice(&[OpCode::Sub as u8, OpCode::Dup as u8, OpCode::JumpIf as u8, 9, 0, 0, 0]);
+    expected.extend_from_slice(&[OpCode::PushBytes as u8, 2, b'o', b'k', OpCode::Len as u8, OpCode::Add as u8]);
+    expected.push(OpCode::Halt as u8);
+
+    let code = asm::assemble(source).unwrap();
+    assert_eq!(code, expected);
+    assert_eq!(ZVM::new(code).run(), Ok(2));
+
+    // Operands name collections, or give their number.
+    assert_eq!(
+        asm::assemble("push 0\ndata outputs\nowner 3\nhalt").unwrap()[9..13],
+        [OpCode::Data as u8, collection::OUTPUTS, OpCode::Owner as u8, collection::CURRENT]
+    );
+    assert_eq!(asm::assemble("push 0x10 ; hex").unwrap()[1], 0x10);
+    assert_eq!(asm::assemble("push -1").unwrap()[1..], (-1i64).to_le_bytes());
+}
+
+#[test]
+fn test_assemble_module() {
+    let source = "
+        .max_stack 4
+        .entry spend main
+        .entry link on_link
+        .const greeting \"hello\"
+        on_link:
+            link_source
+            halt
+        main:
+            push_const greeting
+            len
+            halt
+    ";
+    let code = asm::assemble(source).unwrap();
+    assert!(code.starts_with(&MAGIC));
+    let module = Module::decode(&code).unwrap();
+    assert_eq!(module.max_stack, 4);
+    assert_eq!(module.entry_points, vec![2, 0]);
+    assert_eq!(module.constants, vec![b"hello".to_vec()]);
+    assert_eq!(module.verify(), Ok(()));
+    assert_eq!(ZVM::new(code).run(), Ok(5));
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let source = "
+        .max_stack 8
+        .entry spend main
+        .const c0 0xbeef
+        main:
+            push 2
+            call double
+            push_const c0
+            pop
+            push 0
+            push_bytes 0x0102
+            data_word references
+            link_word target
+            store_local 3
+            halt
+        double:
+            dup
+            add
+            return
+    ";
+    let code = asm::assemble(source).unwrap();
+    let text = asm::disassemble(&code).unwrap();
+    assert!(text.starts_with(".max_stack 8\n.entry spend label_0\n.const c0 0xbeef\nlabel_0:\n    push 2\n    call label_"));
+    assert!(text.contains("    data_word references\n    link_word target\n    store_local 3\n"));
+    assert_eq!(asm::assemble(&text).unwrap(), code);
+
+    // Bare code stays bare.
+    let bare = asm::assemble("push 1\njump end\nend:\nhalt").unwrap();
+    let text = asm::disassemble(&bare).unwrap();
+    assert_eq!(text, "    push 1\n    jump label_14\nlabel_14:\n    halt\n");
+    assert_eq!(asm::assemble(&text).unwrap(), bare);
+}
+
+#[test]
+fn test_assembly_errors() {
+    let error = |source| asm::assemble(source).unwrap_err();
+    assert_eq!(error("push 1\nfrobnicate"), AsmError::UnknownInstruction { line: 2, word: "frobnicate".into() });
+    assert_eq!(error("push"), AsmError::MissingOperand { line: 1, word: "push".into() });
+    assert_eq!(error("add 1"), AsmError::UnexpectedOperand { line: 1, word: "1".into() });
+    assert_eq!(error("push ten"), AsmError::InvalidOperand { line: 1, word: "ten".into() });
+    assert_eq!(error("jump nowhere"), AsmError::UnknownLabel { line: 1, word: "nowhere".into() });
+    assert_eq!(error("a:\na:\nhalt"), AsmError::DuplicateName { line: 2, word: "a".into() });
+    assert_eq!(error("data elsewhere"), AsmError::InvalidOperand { line: 1, word: "elsewhere".into() });
+    assert_eq!(error("push_bytes \"open"), AsmError::UnterminatedString { line: 1 });
+    assert_eq!(error(".origin 0"), AsmError::UnknownDirective { line: 1, word: ".origin".into() });
+    let long = format!("push_bytes 0x{}", "00".repeat(256));
+    assert_eq!(error(&long), AsmError::BytesTooLong { line: 1, len: 256, max: 255 });
+}
@@ -0,0 +1,147 @@
+use zelealem_node::{
+    crypto::{self, sign_data, PublicKey},
+    ledger::{Block, MultisigPolicy, StateObject, Transaction, Witness},
+    node::Node,
+    p2p::DEFAULT_CHAIN_ID,
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+    wallet::{PartialTransaction, WalletError},
+};
+
+// Alice trades her asset for 100 of Bob's value, in one transaction that
+// neither of them can complete alone.
+fn swap(alice: PublicKey, bob: PublicKey) -> (Vec<StateObject>, Transaction) {
+    let asset = StateObject::new(alice, b"deed to plot 7".to_vec(), vec![]);
+    let payment = StateObject::new_value(bob, 100);
+    let tx = Transaction::new(
+        vec![asset.id, payment.id],
+        vec![
+            StateObject::new(bob, asset.data.clone(), vec![]),
+            StateObject::new_value(alice, 100),
+        ],
+        vec![],
+    );
+    (vec![asset, payment], tx)
+}
+
+#[test]
+fn test_atomic_swap_needs_both_parties() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let (inputs, tx) = swap(alice, bob);
+    let mut state = StateDB::new();
+    for so in &inputs {
+        state.add_so(so.clone()).unwrap();
+    }
+
+    // Alice proposes the swap and signs her side.
+    let mut partial = PartialTransaction::new(tx, inputs.clone(), DEFAULT_CHAIN_ID).unwrap();
+    assert_eq!(partial.sign(&alice_sec).unwrap(), vec![0]);
+    assert!(matches!(
+        partial.clone().finalize(DEFAULT_CHAIN_ID),
+        Err(WalletError::Incomplete(ValidationError::InvalidSignature))
+    ));
+
+    // Bob reviews what he is getting and signs his side; only then is it valid.
+    let mut partial = PartialTransaction::decode(&partial.encode()).unwrap();
+    assert_eq!(partial.sign(&bob_sec).unwrap(), vec![1]);
+    let tx = partial.finalize(DEFAULT_CHAIN_ID).unwrap();
+    assert_eq!(tx.witnesses[0].signatures.len(), 1);
+    assert_eq!(tx.witnesses[1].signatures.len(), 1);
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    // A third party can't sign for either side.
+    let (_, mallory_sec) = crypto::generate_keypair();
+    let (_, tx) = swap(alice, bob);
+    let mut partial = PartialTransaction::new(tx, inputs, DEFAULT_CHAIN_ID).unwrap();
+    assert!(matches!(partial.sign(&mallory_sec), Err(WalletError::CannotSign)));
+}
+
+#[test]
+fn test_signature_only_authorizes_its_own_input() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let (inputs, mut tx) = swap(alice, bob);
+    let mut state = StateDB::new();
+    for so in &inputs {
+        state.add_so(so.clone()).unwrap();
+    }
+
+    // Both signatures present, but each in the other's witness.
+    tx.sign_input(0, sign_data(&tx.id, &bob_sec));
+    tx.sign_input(1, sign_data(&tx.id, &alice_sec));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Err(ValidationError::InvalidSignature));
+
+    tx.sign_input(0, sign_data(&tx.id, &alice_sec));
+    tx.sign_input(1, sign_data(&tx.id, &bob_sec));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    // Every input needs exactly one witness.
+    let mut missing = tx.clone();
+    missing.witnesses.pop();
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&missing),
+        Err(ValidationError::WitnessCountMismatch { inputs: 2, witnesses: 1 })
+    );
+    let mut extra = tx.clone();
+    extra.witnesses.push(Witness::default());
+    assert!(matches!(
+        TransactionValidator::new(&state).validate_transaction(&extra),
+        Err(ValidationError::WitnessCountMismatch { inputs: 2, witnesses: 3 })
+    ));
+}
+
+#[test]
+fn test_swap_with_a_multisig_party() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let members: Vec<_> = (0..2).map(|_| crypto::generate_keypair()).collect();
+    let policy = MultisigPolicy::new(2, members.iter().map(|(key, _)| *key).collect()).unwrap();
+    let (inputs, mut tx) = swap(alice, policy.owner_key());
+    let mut state = StateDB::new();
+    for so in &inputs {
+        state.add_so(so.clone()).unwrap();
+    }
+
+    // A policy revealed for the wrong input is refused.
+    let mut misplaced = tx.clone();
+    misplaced.set_policy(0, policy.clone());
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&misplaced),
+        Err(ValidationError::PolicyMismatch(inputs[0].id))
+    );
+
+    tx.set_policy(1, policy.clone());
+    let mut partial = PartialTransaction::new(tx, inputs, DEFAULT_CHAIN_ID).unwrap();
+    partial.sign(&alice_sec).unwrap();
+    assert_eq!(partial.sign(&members[0].1).unwrap(), vec![1]);
+    assert!(matches!(
+        partial.clone().finalize(DEFAULT_CHAIN_ID),
+        Err(WalletError::Incomplete(ValidationError::InsufficientSignatures { found: 1, .. }))
+    ));
+    partial.sign(&members[1].1).unwrap();
+    let tx = partial.finalize(DEFAULT_CHAIN_ID).unwrap();
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+}
+
+#[tokio::test]
+async fn test_node_applies_swap_atomically() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let (inputs, mut tx) = swap(alice, bob);
+    for so in &inputs {
+        node.state_db.add_so(so.clone()).unwrap();
+    }
+
+    // Half-signed, the block is rejected and neither party loses anything.
+    tx.sign_input(0, sign_data(&tx.id, &alice_sec));
+    let block = Block::new(node.chain.get_latest_hash(), alice, vec![tx.clone()], vec![]);
+    assert!(node.process_block(block).is_err());
+    assert!(inputs.iter().all(|so| node.state_db.get_so(&so.id).is_ok()));
+
+    tx.sign_input(1, sign_data(&tx.id, &bob_sec));
+    let block = Block::new(node.chain.get_latest_hash(), alice, vec![tx.clone()], vec![]);
+    node.process_block(block).unwrap();
+    assert!(inputs.iter().all(|so| node.state_db.get_so(&so.id).is_err()));
+    assert!(tx.outputs.iter().all(|so| node.state_db.get_so(&so.id).is_ok()));
+}
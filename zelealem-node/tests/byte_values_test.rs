@@ -0,0 +1,100 @@
+mod common;
+
+use common::assemble;
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::PublicKey,
+    ledger::{StateObject, Transaction},
+    zvm::{ExecutionContext, VmError, GAS_PER_BYTE, MAX_BYTES_LEN, ZVM},
+};
+
+fn run(source: &str) -> Result<i64, VmError> {
+    ZVM::new(assemble(source)).run()
+}
+
+#[test]
+fn test_byte_array_opcodes() {
+    // "hello" ++ " world" has length 11.
+    assert_eq!(run("push_bytes \"hello\"\npush_bytes \" world\"\nconcat\nlen\nhalt"), Ok(11));
+
+    // Slicing "hello world" at 6 for 5 gives "world".
+    let source = "
+        push_bytes \"hello world\"
+        push 6
+        push 5
+        slice
+        push_bytes \"world\"
+        eq
+        halt
+    ";
+    assert_eq!(run(source), Ok(1));
+
+    // Byte arrays order lexicographically.
+    assert_eq!(run("push_bytes \"abc\"\npush_bytes \"abd\"\nlt\nhalt"), Ok(1));
+
+    // Integers convert to and from their little-endian bytes.
+    let source = "
+        push -1234
+        int_to_bytes
+        dup
+        len
+        swap
+        bytes_to_int
+        add
+        halt
+    ";
+    assert_eq!(run(source), Ok(8 - 1234));
+}
+
+#[test]
+fn test_byte_array_errors() {
+    // Mixing types is an error, as is returning bytes as the verdict.
+    assert_eq!(run("push_bytes 0x01\npush 1\neq\nhalt"), Err(VmError::TypeMismatch(OpCode::Eq)));
+    assert_eq!(run("push_bytes 0x01\npush 1\nadd\nhalt"), Err(VmError::TypeMismatch(OpCode::Add)));
+    assert_eq!(run("push_bytes 0x01\nhalt"), Err(VmError::TypeMismatch(OpCode::Halt)));
+    assert_eq!(run("push_bytes \"abc\"\npush 2\npush 2\nslice\nhalt"), Err(VmError::OutOfRange(OpCode::Slice)));
+
+    // Doubling a 255-byte array three times passes the size limit.
+    let source = format!("push_bytes 0x{}\n{}halt", "07".repeat(255), "dup\nconcat\n".repeat(3));
+    assert_eq!(run(&source), Err(VmError::BytesTooLong(255 * 8)));
+    const { assert!(255 * 4 <= MAX_BYTES_LEN) };
+
+    // A PushBytes whose bytes run past the end is malformed.
+    assert_eq!(
+        ZVM::new(vec![OpCode::PushBytes as u8, 4, 1, 2]).run(),
+        Err(VmError::TruncatedImmediate(OpCode::PushBytes))
+    );
+}
+
+#[test]
+fn test_bytes_cost_gas_by_size() {
+    let gas = |len: usize| {
+        let mut vm = ZVM::new(assemble(&format!("push_bytes 0x{}\ndup\nconcat\nlen\nhalt", "00".repeat(len))));
+        vm.run().unwrap();
+        vm.gas_used()
+    };
+    // Each extra byte is paid for when loaded, pushed, duplicated and twice in the concatenation.
+    assert_eq!(gas(101) - gas(1), 100 * 5 * GAS_PER_BYTE);
+}
+
+#[test]
+fn test_whole_fields_as_bytes() {
+    let input = StateObject::new(PublicKey([4; 32]), b"payload".to_vec(), vec![]);
+    let output = StateObject::new_value(PublicKey([4; 32]), 1);
+    let tx = Transaction::new(vec![input.id], vec![output.clone()], vec![]);
+    let context = ExecutionContext {
+        transaction: Some(&tx),
+        inputs: vec![&input],
+        current: Some(&input),
+        ..Default::default()
+    };
+    let run = |source: &str| ZVM::with_context(assemble(source), context.clone(), u64::MAX).run();
+
+    // The output keeps the owner of the object being spent.
+    assert_eq!(run("push 0\nowner outputs\npush 0\nowner current\neq\nhalt"), Ok(1));
+    assert_eq!(run("push 0\ndata inputs\npush_bytes \"payload\"\neq\nhalt"), Ok(1));
+    assert_eq!(
+        run(&format!("push 0\nid outputs\npush_bytes 0x{}\neq\nhalt", hex::encode(output.id))),
+        Ok(1)
+    );
+}
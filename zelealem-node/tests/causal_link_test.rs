@@ -0,0 +1,93 @@
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::{self, sign_data, PublicKey},
+    ledger::{CausalLink, StateObject, Transaction},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+    zvm::VmError,
+};
+
+// Accepts a link only from a source holding a non-zero amount: LINKSOURCE, HALT.
+fn nonzero_source_logic() -> Vec<u8> {
+    vec![OpCode::LinkSource as u8, OpCode::Halt as u8]
+}
+
+// Builds and signs a transaction spending `input`, creating `outputs` and following `links`.
+fn linked_transaction(input: &StateObject, secret: &[u8], outputs: Vec<StateObject>, links: Vec<CausalLink>) -> Transaction {
+    let mut tx = Transaction::new(vec![input.id], outputs, links);
+    tx.sign(sign_data(&tx.id, secret));
+    tx
+}
+
+fn link(source: &StateObject, target: &StateObject) -> CausalLink {
+    CausalLink {
+        source_so_id: source.id,
+        target_so_id: target.id,
+    }
+}
+
+#[test]
+fn test_target_logic_sees_the_source() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 50);
+    let vault = StateObject::new(PublicKey([0x99; 32]), b"vault".to_vec(), nonzero_source_logic());
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+    state.add_so(vault.clone()).unwrap();
+
+    // A deposit created by the transaction itself calls into the vault.
+    let deposit = StateObject::new_value(vault.owner, 50);
+    let tx = linked_transaction(&funds, &alice_sec, vec![deposit.clone()], vec![link(&deposit, &vault)]);
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    let empty = StateObject::new_value(vault.owner, 0);
+    let tx = linked_transaction(&funds, &alice_sec, vec![empty.clone()], vec![link(&empty, &vault)]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::CausalLinkRejected(vault.id))
+    );
+
+    // A target without logic accepts any link, and existing objects can be linked too.
+    let tx = linked_transaction(&funds, &alice_sec, vec![], vec![link(&vault, &funds)]);
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+}
+
+#[test]
+fn test_dangling_links_are_rejected() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 50);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+    let nowhere = StateObject::new_value(alice, 1);
+
+    let tx = linked_transaction(&funds, &alice_sec, vec![], vec![link(&funds, &nowhere)]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::DanglingCausalLink(nowhere.id))
+    );
+
+    let tx = linked_transaction(&funds, &alice_sec, vec![], vec![link(&nowhere, &funds)]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::DanglingCausalLink(nowhere.id))
+    );
+}
+
+#[test]
+fn test_failing_target_logic_rejects_the_transaction() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 50);
+    let broken = StateObject::new(alice, vec![], vec![OpCode::Add as u8, OpCode::Halt as u8]);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+    state.add_so(broken.clone()).unwrap();
+
+    let tx = linked_transaction(&funds, &alice_sec, vec![], vec![link(&funds, &broken)]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::ScriptFailed {
+            id: broken.id,
+            reason: VmError::StackUnderflow(OpCode::Add)
+        })
+    );
+}
@@ -0,0 +1,6 @@
+use zelealem_node::asm;
+
+// Assembles the validation logic a test runs, failing the test if the source is malformed.
+pub fn assemble(source: &str) -> Vec<u8> {
+    asm::assemble(source).unwrap_or_else(|error| panic!("invalid test program: {error}"))
+}
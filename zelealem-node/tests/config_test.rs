@@ -0,0 +1,92 @@
+use std::path::Path;
+use zelealem_node::config::{self, ConfigError, NodeConfig};
+use zelealem_node::keystore::KeystoreError;
+
+#[test]
+fn test_partial_config_uses_defaults() {
+    let toml = r#"
+        listen_addrs = ["/ip4/127.0.0.1/tcp/4001"]
+        block_time_secs = 2
+    "#;
+    let config = NodeConfig::from_toml(toml, Path::new("node.toml")).unwrap();
+
+    assert_eq!(config.block_time_secs, 2);
+    assert_eq!(config.listen_multiaddrs().unwrap()[0].to_string(), "/ip4/127.0.0.1/tcp/4001");
+    // Everything else keeps its default.
+    assert_eq!(config.max_txs_per_block, NodeConfig::default().max_txs_per_block);
+    assert_eq!(config.data_dir, NodeConfig::default().data_dir);
+}
+
+#[test]
+fn test_example_config_parses() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("node.example.toml");
+    let config = NodeConfig::load(&path).unwrap();
+    assert_eq!(config, NodeConfig::default());
+}
+
+#[test]
+fn test_invalid_config_is_rejected() {
+    let unknown_key = NodeConfig::from_toml("blocktime = 5", Path::new("node.toml"));
+    assert!(matches!(unknown_key, Err(ConfigError::Parse { .. })));
+
+    let config = NodeConfig::from_toml(r#"bootstrap_peers = ["not an address"]"#, Path::new("node.toml")).unwrap();
+    assert!(matches!(config.bootstrap_multiaddrs(), Err(ConfigError::InvalidMultiaddr(_))));
+
+    // A zero block time would panic the proposer's timer; a zero batch proposes nothing.
+    let zero_block_time = NodeConfig::from_toml("block_time_secs = 0", Path::new("node.toml"));
+    assert!(matches!(zero_block_time, Err(ConfigError::ZeroValue("block_time_secs"))));
+    let zero_batch = NodeConfig::from_toml("max_txs_per_block = 0", Path::new("node.toml"));
+    assert!(matches!(zero_batch, Err(ConfigError::ZeroValue("max_txs_per_block"))));
+}
+
+#[test]
+fn test_identity_persists_across_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join(config::IDENTITY_FILE);
+
+    let first = config::load_or_generate_identity(&path).unwrap();
+    let second = config::load_or_generate_identity(&path).unwrap();
+    assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_identity_is_private_to_its_owner() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(config::IDENTITY_FILE);
+
+    config::load_or_generate_identity(&path).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+    // A key others can read may already have leaked, so the node won't use it.
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    assert!(matches!(
+        config::load_or_generate_identity(&path),
+        Err(ConfigError::InsecurePermissions(_))
+    ));
+}
+
+#[test]
+fn test_validator_key_persists_across_restarts() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("validator.key");
+
+    let (first_pub, first_sec) = config::load_or_generate_validator_key(&path, "hunter2").unwrap();
+    let (second_pub, second_sec) = config::load_or_generate_validator_key(&path, "hunter2").unwrap();
+    assert_eq!(first_pub, second_pub);
+    assert_eq!(first_sec, second_sec);
+
+    // The key is stored encrypted, not as plain hex.
+    assert!(!std::fs::read_to_string(&path).unwrap().contains(&hex::encode(&first_sec)));
+    assert!(matches!(
+        config::load_or_generate_validator_key(&path, "hunter3"),
+        Err(ConfigError::ValidatorKey(_, KeystoreError::DecryptionFailed))
+    ));
+
+    std::fs::write(&path, "not a keystore").unwrap();
+    assert!(matches!(
+        config::load_or_generate_validator_key(&path, "hunter2"),
+        Err(ConfigError::ValidatorKey(_, KeystoreError::Parse { .. }))
+    ));
+}
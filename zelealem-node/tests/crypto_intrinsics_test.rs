@@ -0,0 +1,109 @@
+mod common;
+
+use common::assemble;
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::{self, hash_pair, sign_data, Hash},
+    ledger::{StateObject, Transaction},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+    zvm::{ExecutionContext, VmError, ZVM},
+};
+
+#[test]
+fn test_hashlocked_object() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let secret = b"open sesame";
+    // Spendable only by revealing the preimage as the data of the first output.
+    let logic = assemble(&format!(
+        "
+            push 0
+            data outputs
+            sha256
+            push_bytes 0x{}
+            eq
+            halt
+        ",
+        hex::encode(crypto::hash_data(secret))
+    ));
+    let locked = StateObject::new(alice, vec![], logic);
+    let mut state = StateDB::new();
+    state.add_so(locked.clone()).unwrap();
+
+    let spend = |data: &[u8]| {
+        let mut tx = Transaction::new(vec![locked.id], vec![StateObject::new(alice, data.to_vec(), vec![])], vec![]);
+        tx.sign(sign_data(&tx.id, &alice_sec));
+        tx
+    };
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&spend(b"open barley")),
+        Err(ValidationError::InputRejected(locked.id))
+    );
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&spend(secret)), Ok(()));
+}
+
+#[test]
+fn test_verify_signature() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, _) = crypto::generate_keypair();
+    let tx = Transaction::new(vec![[1; 32]], vec![], vec![]);
+    let signature = sign_data(&tx.id, &alice_sec);
+    let context = ExecutionContext { transaction: Some(&tx), ..Default::default() };
+    let run = |code: Vec<u8>| ZVM::with_context(code, context.clone(), u64::MAX).run();
+
+    let check = |key: &[u8]| {
+        assemble(&format!(
+            "push_bytes 0x{}\npush_bytes 0x{}\nverify_signature\nhalt",
+            hex::encode(signature),
+            hex::encode(key)
+        ))
+    };
+    assert_eq!(run(check(&alice.0)), Ok(1));
+    assert_eq!(run(check(&bob.0)), Ok(0));
+    assert_eq!(
+        run(check(&alice.0[..31])),
+        Err(VmError::WrongLength { opcode: OpCode::VerifySignature, expected: 32, found: 31 })
+    );
+
+    // Signatures are over the transaction id, so there must be a transaction.
+    assert_eq!(ZVM::new(check(&alice.0)).run(), Err(VmError::NoTransaction(OpCode::VerifySignature)));
+}
+
+#[test]
+fn test_merkle_proof() {
+    // A four-leaf tree.
+    let leaves: Vec<Hash> = (0..4u8).map(|i| crypto::hash_data(&[i])).collect();
+    let left = hash_pair(&leaves[0], &leaves[1]);
+    let right = hash_pair(&leaves[2], &leaves[3]);
+    let root = hash_pair(&left, &right);
+
+    let verify = |leaf: &Hash, index: i64, proof: &[u8]| {
+        let code = assemble(&format!(
+            "push_bytes 0x{}\npush_bytes 0x{}\npush {index}\npush_bytes 0x{}\nmerkle_verify\nhalt",
+            hex::encode(leaf),
+            hex::encode(proof),
+            hex::encode(root)
+        ));
+        ZVM::new(code).run()
+    };
+    // Leaf 2 is proven by its sibling leaf 3, then the left subtree.
+    let proof = [leaves[3], left].concat();
+    assert_eq!(verify(&leaves[2], 2, &proof), Ok(1));
+    assert_eq!(verify(&leaves[2], 3, &proof), Ok(0));
+    assert_eq!(verify(&leaves[2], 6, &proof), Ok(0));
+    assert_eq!(verify(&leaves[2], -2, &proof), Ok(0));
+    assert_eq!(verify(&leaves[1], 2, &proof), Ok(0));
+    assert!(matches!(verify(&leaves[2], 2, &proof[..40]), Err(VmError::WrongLength { found: 40, .. })));
+}
+
+#[test]
+fn test_intrinsics_have_fixed_gas() {
+    let gas = |len: usize| {
+        let code = assemble(&format!("push_bytes 0x{}\nsha256\nlen\nhalt", "00".repeat(len)));
+        let mut vm = ZVM::new(code);
+        assert_eq!(vm.run(), Ok(32));
+        vm.gas_used()
+    };
+    // Only loading the longer input costs more; hashing it doesn't.
+    assert_eq!(gas(200) - gas(100), 2 * 100);
+}
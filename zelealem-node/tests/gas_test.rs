@@ -0,0 +1,91 @@
+mod common;
+
+use common::assemble;
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::{self, sign_data, PublicKey},
+    ledger::{CausalLink, StateObject, Transaction},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError, GAS_PER_SIGNATURE_CHECK, GAS_PER_STATE_OBJECT, TX_BASE_GAS},
+    zvm::{ExecutionContext, VmError, GAS_PER_BYTE, ZVM},
+};
+
+// PUSH 1, then `adds` times PUSH 1 ADD, then HALT.
+fn counting_program(adds: usize) -> Vec<u8> {
+    assemble(&format!("push 1\n{}halt", "push 1\nadd\n".repeat(adds)))
+}
+
+#[test]
+fn test_gas_follows_the_schedule() {
+    let code = counting_program(2);
+    let expected = GAS_PER_BYTE * code.len() as u64
+        + 3 * OpCode::Push.gas_cost()
+        + 2 * OpCode::Add.gas_cost()
+        + OpCode::Halt.gas_cost();
+
+    let mut vm = ZVM::new(code.clone());
+    assert_eq!(vm.run(), Ok(3));
+    assert_eq!(vm.gas_used(), expected);
+
+    // One unit short and the script fails, having used its whole limit.
+    let mut vm = ZVM::with_context(code, ExecutionContext::default(), expected - 1);
+    assert_eq!(vm.run().unwrap_err(), VmError::OutOfGas);
+    assert_eq!(vm.gas_used(), expected - 1);
+}
+
+#[test]
+fn test_reading_objects_costs_per_byte() {
+    let small = StateObject::new_value(PublicKey([1; 32]), 5);
+    let large = StateObject::new(PublicKey([1; 32]), vec![5; 1000], vec![]);
+    let code = assemble("link_source\nhalt");
+    let gas = |source| {
+        let context = ExecutionContext {
+            link_source: Some(source),
+            ..Default::default()
+        };
+        let mut vm = ZVM::with_context(code.clone(), context, u64::MAX);
+        vm.run().unwrap();
+        vm.gas_used()
+    };
+    assert_eq!(gas(&large) - gas(&small), GAS_PER_BYTE * (1000 - 8));
+}
+
+#[test]
+fn test_validator_meters_transactions() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 10);
+    let counter = StateObject::new(PublicKey([2; 32]), vec![], counting_program(100));
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+    state.add_so(counter.clone()).unwrap();
+
+    // A plain spend pays for its size only.
+    let mut tx = Transaction::new(vec![funds.id], vec![], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    let intrinsic = TX_BASE_GAS + GAS_PER_STATE_OBJECT + GAS_PER_BYTE * 8 + GAS_PER_SIGNATURE_CHECK;
+    assert_eq!(TransactionValidator::new(&state).validate_metered(&tx), Ok(intrinsic));
+    assert_eq!(
+        TransactionValidator::new(&state).with_gas_limit(intrinsic - 1).validate_transaction(&tx),
+        Err(ValidationError::OutOfGas { limit: intrinsic - 1 })
+    );
+
+    // Scripts run by causal links add their own gas, within the same limit.
+    let mut tx = Transaction::new(
+        vec![funds.id],
+        vec![],
+        vec![CausalLink {
+            source_so_id: funds.id,
+            target_so_id: counter.id,
+        }],
+    );
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    let mut vm = ZVM::new(counter.validation_logic.clone());
+    vm.run().unwrap();
+    let total = intrinsic + vm.gas_used();
+    assert_eq!(TransactionValidator::new(&state).validate_metered(&tx), Ok(total));
+    assert_eq!(TransactionValidator::new(&state).with_gas_limit(total).validate_metered(&tx), Ok(total));
+    assert_eq!(
+        TransactionValidator::new(&state).with_gas_limit(total - 1).validate_transaction(&tx),
+        Err(ValidationError::OutOfGas { limit: total - 1 })
+    );
+}
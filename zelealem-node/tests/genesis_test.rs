@@ -0,0 +1,95 @@
+use std::path::Path;
+use zelealem_node::{
+    crypto::PublicKey,
+    genesis::{Genesis, GenesisError},
+    node::Node,
+};
+
+fn example_genesis() -> Genesis {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("genesis.example.toml");
+    Genesis::load(&path).unwrap()
+}
+
+#[test]
+fn test_genesis_hash_is_deterministic() {
+    let first = example_genesis().build().unwrap();
+    let second = example_genesis().build().unwrap();
+    assert_eq!(first.chain.get_latest_hash(), second.chain.get_latest_hash());
+
+    // Pinned so that an accidental change to the genesis encoding is caught:
+    // every deployed node would otherwise disagree on block zero.
+    assert_eq!(
+        hex::encode(first.chain.get_latest_hash()),
+        "919f59164411f6f41b7ab920b4fc93a3c13e48926a901dce9d30812d2dda3998"
+    );
+}
+
+#[test]
+fn test_genesis_hash_commits_to_spec() {
+    let base = example_genesis().build().unwrap().chain.get_latest_hash();
+
+    let mut other_chain = example_genesis();
+    other_chain.chain_id += 1;
+    assert_ne!(other_chain.build().unwrap().chain.get_latest_hash(), base);
+
+    let mut other_stake = example_genesis();
+    other_stake.validators[0].stake += 1;
+    assert_ne!(other_stake.build().unwrap().chain.get_latest_hash(), base);
+}
+
+#[test]
+fn test_genesis_populates_state_and_validators() {
+    let genesis = example_genesis();
+    let state = genesis.build().unwrap();
+
+    assert_eq!(state.validator_set.validators.len(), 2);
+    assert_eq!(state.validator_set.validators[&PublicKey([0x11; 32])].stake, 1000);
+
+    let allocation_tx = &genesis.block().transactions[0];
+    assert_eq!(allocation_tx.outputs.len(), 2);
+    for so in &allocation_tx.outputs {
+        assert!(state.state_db.get_so(&so.id).is_ok());
+    }
+}
+
+#[test]
+fn test_invalid_genesis_is_rejected() {
+    let mut duplicate = example_genesis();
+    duplicate.validators.push(duplicate.validators[0].clone());
+    assert!(matches!(duplicate.build(), Err(GenesisError::DuplicateValidator(_))));
+
+    let mut underfunded = example_genesis();
+    underfunded.validators[1].stake = 1;
+    assert!(matches!(underfunded.build(), Err(GenesisError::InsufficientStake(_))));
+
+    let mut empty = example_genesis();
+    empty.validators.clear();
+    assert!(matches!(empty.build(), Err(GenesisError::NoValidators)));
+
+    let mut bad_logic = example_genesis();
+    bad_logic.allocations[0].validation_logic = vec![0x00, b'Z', b'V', b'M', 99];
+    assert!(matches!(bad_logic.build(), Err(GenesisError::InvalidLogic { .. })));
+
+    let bad_hex = r#"
+        chain_id = 1
+        [[validators]]
+        pub_key = "not hex"
+        stake = 1
+    "#;
+    assert!(matches!(
+        Genesis::from_toml(bad_hex, Path::new("genesis.toml")),
+        Err(GenesisError::Parse { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_node_starts_from_genesis() {
+    let genesis = example_genesis();
+    let node = Node::with_genesis(libp2p::identity::Keypair::generate_ed25519(), &genesis)
+        .await
+        .unwrap();
+
+    assert_eq!(node.chain_id, genesis.chain_id);
+    assert_eq!(node.chain.get_latest_hash(), genesis.block().id);
+    assert_eq!(node.validator_set.validators.len(), 2);
+}
@@ -0,0 +1,92 @@
+use zelealem_node::{
+    crypto::{self, DerivationPath, KeyDerivationError, HARDENED_OFFSET},
+    keystore::{self, Keystore, KeystoreError, ScryptParams, SecretKind},
+    wallet::{self, WalletError},
+};
+
+const TEST_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+#[test]
+fn test_mnemonic_to_seed_matches_bip39() {
+    // Test vector from the BIP-39 reference implementation.
+    let seed = crypto::mnemonic_to_seed(TEST_MNEMONIC, "TREZOR").unwrap();
+    assert_eq!(
+        hex::encode(seed),
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+    );
+
+    let mnemonic = crypto::generate_mnemonic();
+    assert_eq!(mnemonic.split_whitespace().count(), 24);
+    assert_eq!(crypto::mnemonic_to_seed(&mnemonic, "").unwrap(), crypto::mnemonic_to_seed(&mnemonic, "").unwrap());
+
+    // The last word carries a checksum, so a typo is caught.
+    let typo = TEST_MNEMONIC.replace("about", "abandon");
+    assert!(matches!(crypto::mnemonic_to_seed(&typo, ""), Err(KeyDerivationError::InvalidMnemonic(_))));
+}
+
+#[test]
+fn test_account_keys_are_deterministic_and_distinct() {
+    let seed = crypto::mnemonic_to_seed(TEST_MNEMONIC, "").unwrap();
+
+    let (account0, secret0) = crypto::derive_account_keypair(&seed, 0).unwrap();
+    let (account1, _) = crypto::derive_account_keypair(&seed, 1).unwrap();
+    assert_eq!(crypto::derive_account_keypair(&seed, 0).unwrap(), (account0, secret0.clone()));
+    assert_ne!(account0, account1);
+    assert_eq!(crypto::public_key_from_secret(&secret0), account0);
+
+    // Pinned so a change to the derivation scheme can't silently move users' funds.
+    assert_eq!(hex::encode(account0.0), "c06c0a405362ddab353bb3054777d81c63c67f29df506e3ce0fd759bb2f3cce3");
+
+    let other_seed = crypto::mnemonic_to_seed(TEST_MNEMONIC, "passphrase").unwrap();
+    assert_ne!(crypto::derive_account_keypair(&other_seed, 0).unwrap().0, account0);
+}
+
+#[test]
+fn test_derivation_paths() {
+    let path: DerivationPath = "m/44'/7323'/5'".parse().unwrap();
+    assert_eq!(path, DerivationPath::account(5).unwrap());
+    assert_eq!(path.to_string(), "m/44'/7323'/5'");
+
+    let seed = [7u8; 64];
+    assert_eq!(
+        crypto::derive_keypair(&seed, &path).unwrap(),
+        crypto::derive_account_keypair(&seed, 5).unwrap()
+    );
+
+    // Public keys are hashes, so only hardened derivation makes sense.
+    assert_eq!("m/44'/0".parse::<DerivationPath>(), Err(KeyDerivationError::NonHardenedIndex(0)));
+    assert!(matches!("44'/0'".parse::<DerivationPath>(), Err(KeyDerivationError::InvalidPath(_))));
+    assert!(matches!(format!("m/{}'", HARDENED_OFFSET).parse::<DerivationPath>(), Err(KeyDerivationError::InvalidPath(_))));
+    assert!(DerivationPath::account(HARDENED_OFFSET).is_err());
+}
+
+#[test]
+fn test_seed_keystore_and_wallet_accounts() {
+    let seed = crypto::mnemonic_to_seed(TEST_MNEMONIC, "").unwrap();
+    let params = ScryptParams { log_n: 4, r: 8, p: 1 };
+    let keystore = Keystore::seal(SecretKind::Seed, &seed, "pw", params).unwrap();
+
+    // A seed keystore advertises account 0 and decrypts to the seed.
+    let (account0, _) = crypto::derive_account_keypair(&seed, 0).unwrap();
+    assert_eq!(keystore.public_key, account0);
+    let (_, secret) = keystore.decrypt("pw").unwrap();
+    assert_eq!(
+        wallet::account_keypair(keystore.kind, &secret, 3).unwrap(),
+        crypto::derive_account_keypair(&seed, 3).unwrap()
+    );
+
+    // A seed must never be mistaken for a secret key.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keystore.json");
+    keystore.save(&path).unwrap();
+    assert!(matches!(keystore::load_key(&path, "pw"), Err(KeystoreError::WrongKind { .. })));
+
+    // A single imported key only has account 0.
+    let (public_key, secret_key) = crypto::generate_keypair();
+    assert_eq!(wallet::account_keypair(SecretKind::Key, &secret_key, 0).unwrap().0, public_key);
+    assert!(matches!(
+        wallet::account_keypair(SecretKind::Key, &secret_key, 1),
+        Err(WalletError::NoSuchAccount(1))
+    ));
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::assemble;
+use zelealem_node::{
+    bytecode::{collection, OpCode},
+    crypto::{self, sign_data, PublicKey},
+    ledger::{CausalLink, StateObject, Transaction},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+    zvm::{ExecutionContext, VmError, ZVM},
+};
+
+fn spend(input: &StateObject, secret: &[u8], outputs: Vec<StateObject>) -> Transaction {
+    let mut tx = Transaction::new(vec![input.id], outputs, vec![]);
+    tx.sign(sign_data(&tx.id, secret));
+    tx
+}
+
+#[test]
+fn test_time_locked_object() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    // Spendable once the block height exceeds 9.
+    let logic = assemble("block_height\npush 9\ngt\nhalt");
+    let locked = StateObject::new(alice, 50u64.to_le_bytes().to_vec(), logic);
+    let mut state = StateDB::new();
+    state.add_so(locked.clone()).unwrap();
+
+    let tx = spend(&locked, &alice_sec, vec![StateObject::new_value(alice, 50)]);
+    assert_eq!(
+        TransactionValidator::new(&state).with_block_height(9).validate_transaction(&tx),
+        Err(ValidationError::InputRejected(locked.id))
+    );
+    assert_eq!(TransactionValidator::new(&state).with_block_height(10).validate_transaction(&tx), Ok(()));
+}
+
+#[test]
+fn test_covenant_restricts_outputs() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let logic = assemble(
+        "
+        ; The first output must keep the owner (first word of the key) and at least the amount.
+            push 0              ; object index
+            push 0              ; byte offset
+            owner_word outputs
+            push 0
+            push 0
+            owner_word current
+            eq
+            push 0
+            push 0
+            data_word outputs
+            push 0
+            push 0
+            data_word current
+            lt
+            not
+            and
+            halt
+        ",
+    );
+    let vault = StateObject::new(alice, 100u64.to_le_bytes().to_vec(), logic);
+    let mut state = StateDB::new();
+    state.add_so(vault.clone()).unwrap();
+    let validator = TransactionValidator::new(&state);
+
+    let kept = spend(&vault, &alice_sec, vec![StateObject::new_value(alice, 100)]);
+    assert_eq!(validator.validate_transaction(&kept), Ok(()));
+
+    let drained = spend(&vault, &alice_sec, vec![StateObject::new_value(alice, 99)]);
+    assert_eq!(validator.validate_transaction(&drained), Err(ValidationError::InputRejected(vault.id)));
+
+    let moved = spend(&vault, &alice_sec, vec![StateObject::new_value(PublicKey([0xee; 32]), 100)]);
+    assert_eq!(validator.validate_transaction(&moved), Err(ValidationError::InputRejected(vault.id)));
+
+    // With no outputs at all the script faults, which rejects the transaction too.
+    let burned = spend(&vault, &alice_sec, vec![]);
+    assert_eq!(
+        validator.validate_transaction(&burned),
+        Err(ValidationError::ScriptFailed {
+            id: vault.id,
+            reason: VmError::NoSuchObject {
+                collection: collection::OUTPUTS,
+                index: 0
+            }
+        })
+    );
+}
+
+#[test]
+fn test_introspection_opcodes() {
+    let first = StateObject::new_value(PublicKey([1; 32]), 7);
+    let second = StateObject::new(PublicKey([2; 32]), vec![0xaa; 12], vec![]);
+    let output = StateObject::new_value(PublicKey([3; 32]), 7);
+    let link = CausalLink {
+        source_so_id: output.id,
+        target_so_id: first.id,
+    };
+    let tx = Transaction::new(vec![first.id, second.id], vec![output.clone()], vec![link]);
+    let context = ExecutionContext {
+        transaction: Some(&tx),
+        inputs: vec![&first, &second],
+        current: Some(&second),
+        block_height: 42,
+        ..Default::default()
+    };
+    let run = |source: &str| ZVM::with_context(assemble(source), context.clone(), u64::MAX).run();
+    let word = |bytes: &[u8]| i64::from_le_bytes(bytes[..8].try_into().unwrap());
+
+    assert_eq!(run("count inputs\nhalt"), Ok(2));
+    assert_eq!(run("count outputs\nhalt"), Ok(1));
+    assert_eq!(run("count links\nhalt"), Ok(1));
+    assert_eq!(run("count references\nhalt"), Ok(0));
+    assert_eq!(run("self_index\nhalt"), Ok(1));
+    assert_eq!(run("block_height\nhalt"), Ok(42));
+    assert_eq!(run("push 1\ndata_len inputs\nhalt"), Ok(12));
+
+    // Words past the end of the data are zero-padded.
+    assert_eq!(
+        run("push 1\npush 8\ndata_word inputs\nhalt"),
+        Ok(i64::from_le_bytes([0xaa, 0xaa, 0xaa, 0xaa, 0, 0, 0, 0]))
+    );
+    assert_eq!(run("push 0\npush 24\nowner_word current\nhalt"), Ok(word(&[2; 8])));
+    assert_eq!(run("push 0\npush 8\nid_word outputs\nhalt"), Ok(word(&output.id[8..])));
+    assert_eq!(
+        run("push 0\npush 0\nlink_word target\nhalt"),
+        Ok(word(&first.id))
+    );
+}
+
+#[test]
+fn test_introspection_errors() {
+    let so = StateObject::new_value(PublicKey([1; 32]), 7);
+    let context = ExecutionContext {
+        inputs: vec![&so],
+        ..Default::default()
+    };
+    let run = |source: &str| ZVM::with_context(assemble(source), context.clone(), u64::MAX).run();
+
+    assert_eq!(
+        run("count outputs\nhalt"),
+        Err(VmError::NoTransaction(OpCode::Count))
+    );
+    assert_eq!(
+        run("count 9\nhalt"),
+        Err(VmError::InvalidCollection(OpCode::Count, 9))
+    );
+    assert_eq!(
+        run("push 0\npush 0\ndata_word links\nhalt"),
+        Err(VmError::InvalidCollection(OpCode::DataWord, collection::LINKS))
+    );
+    assert_eq!(
+        run("push 1\npush 0\ndata_word inputs\nhalt"),
+        Err(VmError::NoSuchObject { collection: collection::INPUTS, index: 1 })
+    );
+    assert_eq!(
+        run("push 0\npush -8\ndata_word inputs\nhalt"),
+        Err(VmError::NegativeOffset(OpCode::DataWord))
+    );
+    assert_eq!(run("self_index\nhalt"), Ok(-1));
+}
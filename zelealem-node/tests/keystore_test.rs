@@ -0,0 +1,75 @@
+use zelealem_node::{
+    crypto,
+    keystore::{self, Keystore, KeystoreError, ScryptParams},
+};
+
+// Cheap scrypt parameters so the tests stay fast.
+const TEST_PARAMS: ScryptParams = ScryptParams { log_n: 4, r: 8, p: 1 };
+
+#[test]
+fn test_keystore_round_trip_through_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("wallet").join("keystore.json");
+    let (public_key, secret_key) = crypto::generate_keypair();
+
+    keystore::save_key(&path, &secret_key, "correct horse").unwrap();
+
+    // The public key is readable without the password; the secret key is not stored in the clear.
+    let stored = Keystore::load(&path).unwrap();
+    assert_eq!(stored.public_key, public_key);
+    assert!(!std::fs::read_to_string(&path).unwrap().contains(&hex::encode(&secret_key)));
+
+    assert_eq!(keystore::load_key(&path, "correct horse").unwrap(), (public_key, secret_key));
+}
+
+#[test]
+fn test_wrong_password_is_rejected() {
+    let (_, secret_key) = crypto::generate_keypair();
+    let keystore = Keystore::encrypt_with_params(&secret_key, "correct horse", TEST_PARAMS).unwrap();
+
+    assert!(matches!(keystore.decrypt("battery staple"), Err(KeystoreError::DecryptionFailed)));
+    assert!(matches!(keystore.decrypt(""), Err(KeystoreError::DecryptionFailed)));
+}
+
+#[test]
+fn test_tampered_keystore_is_rejected() {
+    let (_, secret_key) = crypto::generate_keypair();
+    let keystore = Keystore::encrypt_with_params(&secret_key, "pw", TEST_PARAMS).unwrap();
+    assert!(keystore.decrypt("pw").is_ok());
+
+    let mut flipped_ciphertext = keystore.clone();
+    flipped_ciphertext.ciphertext[0] ^= 1;
+    assert!(matches!(flipped_ciphertext.decrypt("pw"), Err(KeystoreError::DecryptionFailed)));
+
+    // Swapping in someone else's address must not go unnoticed.
+    let mut swapped_key = keystore.clone();
+    swapped_key.public_key = crypto::generate_keypair().0;
+    assert!(matches!(swapped_key.decrypt("pw"), Err(KeystoreError::DecryptionFailed)));
+
+    let mut weakened_kdf = keystore.clone();
+    weakened_kdf.kdf.params.log_n = 3;
+    assert!(matches!(weakened_kdf.decrypt("pw"), Err(KeystoreError::DecryptionFailed)));
+
+    let mut truncated = keystore.clone();
+    truncated.ciphertext.truncate(8);
+    assert!(matches!(truncated.decrypt("pw"), Err(KeystoreError::DecryptionFailed)));
+}
+
+#[test]
+fn test_unknown_format_is_rejected() {
+    let (_, secret_key) = crypto::generate_keypair();
+    let keystore = Keystore::encrypt_with_params(&secret_key, "pw", TEST_PARAMS).unwrap();
+
+    let mut future = keystore.clone();
+    future.version = 2;
+    assert!(matches!(future.decrypt("pw"), Err(KeystoreError::UnsupportedVersion(2))));
+
+    let mut other_kdf = keystore.clone();
+    other_kdf.kdf.algorithm = "pbkdf2".to_string();
+    assert!(matches!(other_kdf.decrypt("pw"), Err(KeystoreError::UnsupportedAlgorithm(_))));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keystore.json");
+    std::fs::write(&path, "{}").unwrap();
+    assert!(matches!(Keystore::load(&path), Err(KeystoreError::Parse { .. })));
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use common::assemble;
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::{self, sign_data, PublicKey},
+    ledger::{CausalLink, StateObject, Transaction},
+    module::{Module, ModuleError, MAGIC, MAX_FUNCTIONS, MAX_INSTRUCTIONS},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+    zvm::{VmError, ZVM},
+};
+
+fn verify(source: &str) -> Result<(), ModuleError> {
+    Module::from_logic(&assemble(source)).and_then(|module| module.verify())
+}
+
+#[test]
+fn test_module_encoding() {
+    let module = Module {
+        max_stack: 8,
+        entry_points: vec![0, 2],
+        constants: vec![b"hello".to_vec(), vec![]],
+        code: vec![OpCode::PushConst as u8, 0, OpCode::Len as u8, OpCode::Halt as u8],
+    };
+    let bytes = module.encode();
+    assert!(bytes.starts_with(&MAGIC));
+    assert_eq!(Module::decode(&bytes), Ok(module.clone()));
+    assert_eq!(Module::from_logic(&bytes), Ok(module.clone()));
+    assert_eq!(ZVM::new(bytes.clone()).run(), Ok(5));
+    let source = "
+        .max_stack 8
+        .entry link length
+        .const greeting \"hello\"
+        .const empty 0x
+            push_const greeting
+        length:
+            len
+            halt
+    ";
+    assert_eq!(assemble(source), bytes);
+
+    // A bare opcode stream is a module with one entry point and no constants.
+    let bare = assemble("push 3\nhalt");
+    assert_eq!(Module::from_logic(&bare), Ok(Module::new(bare.clone())));
+
+    let mut other_version = bytes.clone();
+    other_version[MAGIC.len()] = 2;
+    assert_eq!(Module::decode(&other_version), Err(ModuleError::UnsupportedVersion(2)));
+    assert_eq!(Module::decode(&bytes[..bytes.len() - 1]), Err(ModuleError::Truncated("code section")));
+    assert_eq!(Module::decode(&[bytes.clone(), vec![0]].concat()), Err(ModuleError::TrailingBytes(1)));
+    assert_eq!(Module::decode(&bare), Err(ModuleError::BadMagic));
+    assert_eq!(
+        ZVM::new(bytes[..18].to_vec()).run(),
+        Err(VmError::InvalidModule(Box::new(ModuleError::Truncated("constant pool"))))
+    );
+}
+
+#[test]
+fn test_verifier_accepts_well_formed_code() {
+    let source = "
+        ; Doubles 2 in a function, then halts with the result unless it is zero.
+            push 2
+            call double
+            dup
+            jump_if done
+            pop
+            push 0
+        done:
+            halt
+        double:
+            dup
+            add
+            return
+    ";
+    assert_eq!(verify(source), Ok(()));
+    assert_eq!(ZVM::new(assemble(source)).run(), Ok(4));
+}
+
+#[test]
+fn test_verifier_rejects_malformed_code() {
+    assert_eq!(verify("push 1\nhalt\nhalt"), Err(ModuleError::Unreachable(10)));
+    assert_eq!(verify("push 1"), Err(ModuleError::RunsOffEnd(0)));
+    assert_eq!(verify("return"), Err(ModuleError::ReturnOutsideCall(0)));
+    assert_eq!(verify("push 1\nadd\nhalt"), Err(ModuleError::StackUnderflow(9)));
+    assert_eq!(
+        verify("jump 3\nhalt"),
+        Err(ModuleError::Code(VmError::InvalidJumpTarget { at: 0, target: 3 }))
+    );
+    assert_eq!(Module::new(vec![0x7f]).verify(), Err(ModuleError::Code(VmError::InvalidOpcode(0x7f))));
+    assert_eq!(
+        verify("push_const 0\nhalt"),
+        Err(ModuleError::InvalidConstant { opcode: OpCode::PushConst, at: 0, index: 0 })
+    );
+
+    // The two paths into the Halt at 32 disagree on the depth.
+    let source = "
+            push 5
+            push 1
+            jump_if end
+            push 7
+        end:
+            halt
+    ";
+    assert_eq!(verify(source), Err(ModuleError::StackImbalance { at: 32, expected: 1, found: 2 }));
+
+    // The function at 15 returns having pushed one value on one path and none on the other.
+    let source = "
+            push 9
+            call function
+            halt
+        function:
+            push 1
+            jump_if push_one
+            return
+        push_one:
+            push 1
+            return
+    ";
+    assert_eq!(verify(source), Err(ModuleError::InconsistentReturn { function: 15, expected: 1, found: 0 }));
+    assert_eq!(verify("call f\nhalt\nf:\ncall f\nreturn"), Err(ModuleError::Recursion(6)));
+
+    // The function at 11 falls through into the one at 20, so they share its Return.
+    let source = "
+            call f
+            call g
+            halt
+        f:
+            push 1
+        g:
+            return
+    ";
+    assert_eq!(verify(source), Err(ModuleError::SharedCode(20)));
+
+    // Three values don't fit a declared maximum of two.
+    let source = |max_stack| format!(".max_stack {max_stack}\npush 1\npush 2\npush 3\nhalt");
+    assert_eq!(verify(&source(2)), Err(ModuleError::StackTooDeep { at: 18, max: 2 }));
+    assert_eq!(verify(&source(3)), Ok(()));
+}
+
+#[test]
+fn test_outputs_are_verified_on_creation() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 50);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+
+    let broken = StateObject::new(alice, vec![], assemble("return"));
+    let mut tx = Transaction::new(vec![funds.id], vec![broken.clone()], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::InvalidLogic { id: broken.id, reason: ModuleError::ReturnOutsideCall(0) })
+    );
+}
+
+#[test]
+fn test_entry_point_depends_on_how_logic_is_run() {
+    let source = "
+        ; Never agrees to be spent, but accepts links from a non-zero source.
+        .entry spend spend
+        .entry link on_link
+        spend:
+            push 0
+            halt
+        on_link:
+            link_source
+            halt
+    ";
+    assert_eq!(verify(source), Ok(()));
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let vault = StateObject::new(alice, vec![], assemble(source));
+    let funds = StateObject::new_value(alice, 50);
+    let mut state = StateDB::new();
+    state.add_so(vault.clone()).unwrap();
+    state.add_so(funds.clone()).unwrap();
+
+    let deposit = StateObject::new_value(PublicKey([1; 32]), 50);
+    let links = vec![CausalLink { source_so_id: deposit.id, target_so_id: vault.id }];
+    let mut tx = Transaction::new(vec![funds.id], vec![deposit], links);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    let mut tx = Transaction::new(vec![vault.id], vec![], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::InputRejected(vault.id))
+    );
+}
+
+// Builds `calls` calls into consecutive pairs of a `pairs`-long `dup; pop` sled ending in Return.
+fn sled(pairs: usize, calls: usize) -> String {
+    let mut source = String::from("push 1\n");
+    for i in 0..calls {
+        source += &format!("call s{i}\n");
+    }
+    source += "halt\n";
+    for i in 0..pairs {
+        if i < calls {
+            source += &format!("s{i}:\n");
+        }
+        source += "dup\npop\n";
+    }
+    source + "return\n"
+}
+
+#[test]
+fn test_verification_is_linear_and_bounded() {
+    // Calls into many offsets of one sled would each re-walk the rest of it.
+    let started = std::time::Instant::now();
+    assert!(matches!(verify(&sled(5_000, 4_000)), Err(ModuleError::SharedCode(_))));
+    assert_eq!(verify(&sled(5_000, 1)), Ok(()));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    let long = format!("push 1\n{}halt", "dup\npop\n".repeat(MAX_INSTRUCTIONS / 2));
+    assert_eq!(verify(&long), Err(ModuleError::TooManyInstructions(MAX_INSTRUCTIONS + 2)));
+
+    let mut many = String::from("push 1\n");
+    for i in 0..=MAX_FUNCTIONS {
+        many += &format!("call f{i}\n");
+    }
+    many += "halt\n";
+    for i in 0..=MAX_FUNCTIONS {
+        many += &format!("f{i}:\nreturn\n");
+    }
+    assert_eq!(verify(&many), Err(ModuleError::TooManyFunctions));
+}
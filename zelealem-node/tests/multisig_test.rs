@@ -0,0 +1,157 @@
+use zelealem_node::{
+    crypto::{self, sign_data, PublicKey},
+    ledger::{MultisigPolicy, PolicyError, StateObject, Transaction, MAX_MULTISIG_KEYS},
+    p2p::DEFAULT_CHAIN_ID,
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError, GAS_PER_SIGNATURE_CHECK},
+    wallet::{self, PartialTransaction, WalletError},
+};
+
+// Three keypairs and the 2-of-3 policy over them.
+fn treasury() -> (Vec<(PublicKey, Vec<u8>)>, MultisigPolicy) {
+    let members: Vec<_> = (0..3).map(|_| crypto::generate_keypair()).collect();
+    let policy = MultisigPolicy::new(2, members.iter().map(|(key, _)| *key).collect()).unwrap();
+    (members, policy)
+}
+
+#[test]
+fn test_policy_construction() {
+    let (a, b, c) = (PublicKey([3; 32]), PublicKey([1; 32]), PublicKey([2; 32]));
+
+    // Key order and duplicates don't change the policy or its owner key.
+    let policy = MultisigPolicy::new(2, vec![a, b, c, b]).unwrap();
+    assert_eq!(policy.keys, vec![b, c, a]);
+    assert_eq!(policy.owner_key(), MultisigPolicy::new(2, vec![c, a, b]).unwrap().owner_key());
+    assert_ne!(policy.owner_key(), MultisigPolicy::new(3, vec![a, b, c]).unwrap().owner_key());
+
+    assert_eq!(
+        MultisigPolicy::new(4, vec![a, b, c]),
+        Err(PolicyError::InvalidThreshold { threshold: 4, keys: 3 })
+    );
+    assert!(MultisigPolicy::new(0, vec![a]).is_err());
+    let too_many = (0..=MAX_MULTISIG_KEYS as u8).map(|i| PublicKey([i; 32])).collect();
+    assert_eq!(MultisigPolicy::new(1, too_many), Err(PolicyError::TooManyKeys(MAX_MULTISIG_KEYS + 1)));
+
+    let unsorted = MultisigPolicy { threshold: 1, keys: vec![a, b] };
+    assert_eq!(unsorted.validate(), Err(PolicyError::UnsortedKeys));
+}
+
+#[test]
+fn test_threshold_signatures() {
+    let (members, policy) = treasury();
+    let funds = StateObject::new_value(policy.owner_key(), 500);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+
+    let mut tx = Transaction::new(vec![funds.id], vec![StateObject::new_value(PublicKey([9; 32]), 500)], vec![]);
+    tx.sign(sign_data(&tx.id, &members[0].1));
+
+    // Without the policy revealed, the owner key is treated as a plain key nobody holds.
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Err(ValidationError::InvalidSignature));
+
+    tx.set_policy(0, policy.clone());
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::InsufficientSignatures { owner: policy.owner_key(), required: 2, found: 1 })
+    );
+
+    // The same member signing twice still counts once, and outsiders don't count.
+    let mut padded = tx.clone();
+    padded.sign(sign_data(&tx.id, &members[0].1));
+    padded.sign(sign_data(&tx.id, &crypto::generate_keypair().1));
+    assert!(matches!(
+        TransactionValidator::new(&state).validate_transaction(&padded),
+        Err(ValidationError::InsufficientSignatures { found: 1, .. })
+    ));
+
+    tx.sign(sign_data(&tx.id, &members[2].1));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    // A malformed policy is refused outright.
+    let mut bad_policy = tx.clone();
+    bad_policy.witnesses[0].policy.as_mut().unwrap().threshold = 0;
+    assert!(matches!(
+        TransactionValidator::new(&state).validate_transaction(&bad_policy),
+        Err(ValidationError::InvalidPolicy(_))
+    ));
+}
+
+#[test]
+fn test_signature_checks_are_metered() {
+    let (members, policy) = treasury();
+    let funds = StateObject::new_value(policy.owner_key(), 500);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+
+    let mut tx = Transaction::new(vec![funds.id], vec![], vec![]);
+    tx.set_policy(0, policy.clone());
+    tx.sign(sign_data(&tx.id, &members[0].1));
+    tx.sign(sign_data(&tx.id, &members[1].1));
+    let gas = TransactionValidator::new(&state).validate_metered(&tx).unwrap();
+
+    // Each extra signature may be checked against all three keys, and is paid for that way.
+    let mut padded = tx.clone();
+    padded.sign(sign_data(&tx.id, &crypto::generate_keypair().1));
+    assert_eq!(TransactionValidator::new(&state).validate_metered(&padded), Ok(gas + 3 * GAS_PER_SIGNATURE_CHECK));
+
+    // So piling on signatures runs out of gas before any of them is checked.
+    let outsider = crypto::generate_keypair().1;
+    for _ in 0..100 {
+        padded.sign(sign_data(&tx.id, &outsider));
+    }
+    assert_eq!(
+        TransactionValidator::new(&state).with_gas_limit(gas + 100 * GAS_PER_SIGNATURE_CHECK).validate_transaction(&padded),
+        Err(ValidationError::OutOfGas { limit: gas + 100 * GAS_PER_SIGNATURE_CHECK })
+    );
+}
+
+#[test]
+fn test_every_owner_must_sign() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let alice_so = StateObject::new_value(alice, 1);
+    let bob_so = StateObject::new_value(bob, 2);
+    let mut state = StateDB::new();
+    state.add_so(alice_so.clone()).unwrap();
+    state.add_so(bob_so.clone()).unwrap();
+
+    let mut tx = Transaction::new(vec![alice_so.id, bob_so.id], vec![], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Err(ValidationError::InvalidSignature));
+
+    tx.sign(sign_data(&tx.id, &bob_sec));
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+}
+
+#[test]
+fn test_multisig_offline_signing() {
+    let (members, policy) = treasury();
+    let owned = vec![StateObject::new_value(policy.owner_key(), 300)];
+    let mut state = StateDB::new();
+    state.add_so(owned[0].clone()).unwrap();
+
+    let mut tx = wallet::build_transfer(&owned, PublicKey([9; 32]), 100, policy.owner_key()).unwrap();
+    tx.set_policy(0, policy.clone());
+    let mut partial = PartialTransaction::new(tx, owned, DEFAULT_CHAIN_ID).unwrap();
+
+    // Outsiders can't sign; members pass the transaction along, adding signatures.
+    let (_, outsider) = crypto::generate_keypair();
+    assert!(matches!(partial.sign(&outsider), Err(WalletError::CannotSign)));
+
+    partial.sign(&members[1].1).unwrap();
+    partial.sign(&members[1].1).unwrap();
+    assert_eq!(partial.transaction.witnesses[0].signatures.len(), 1);
+    assert!(matches!(
+        partial.clone().finalize(DEFAULT_CHAIN_ID),
+        Err(WalletError::Incomplete(ValidationError::InsufficientSignatures { found: 1, .. }))
+    ));
+
+    let mut partial = PartialTransaction::decode(&partial.encode()).unwrap();
+    partial.sign(&members[0].1).unwrap();
+    let tx = partial.finalize(DEFAULT_CHAIN_ID).unwrap();
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    // Change goes back to the treasury.
+    assert_eq!(tx.outputs[1].owner, policy.owner_key());
+    assert_eq!(tx.outputs[1].amount(), Some(200));
+}
@@ -0,0 +1,156 @@
+use libp2p::{gossipsub, PeerId};
+use zelealem_node::{
+    crypto::{self, sign_data, PublicKey},
+    ledger::{Block, CausalLink, StateObject, Transaction},
+    node::Node,
+    p2p,
+    peer_manager::PeerManager,
+    topics,
+};
+
+// Wraps raw bytes in a gossipsub message on the given topic, as if a peer had sent it.
+fn gossip_message(topic: gossipsub::IdentTopic, data: Vec<u8>) -> gossipsub::Message {
+    gossipsub::Message {
+        source: Some(PeerId::random()),
+        data,
+        sequence_number: Some(0),
+        topic: topic.hash(),
+    }
+}
+
+#[test]
+fn test_peer_is_banned_after_threshold() {
+    let mut peers = PeerManager::new(3);
+    let offender = PeerId::random();
+    let bystander = PeerId::random();
+
+    assert!(!peers.record_invalid_message(offender));
+    assert!(!peers.record_invalid_message(bystander));
+    assert!(!peers.record_invalid_message(offender));
+    assert_eq!(peers.strikes(&offender), 2);
+    assert!(!peers.is_banned(&offender));
+
+    // The third strike crosses the threshold and is reported exactly once.
+    assert!(peers.record_invalid_message(offender));
+    assert!(peers.is_banned(&offender));
+    assert!(!peers.record_invalid_message(offender));
+
+    assert!(!peers.is_banned(&bystander));
+    assert_eq!(peers.strikes(&bystander), 1);
+}
+
+#[tokio::test]
+async fn test_undecodable_gossip_is_rejected() {
+    let mut node = Node::new().await;
+
+    let garbage = vec![0xff; 16];
+    let tx_message = gossip_message(topics::transactions_topic(), garbage.clone());
+    let block_message = gossip_message(topics::blocks_topic(), garbage);
+
+    assert!(matches!(node.handle_gossip_message(&tx_message), gossipsub::MessageAcceptance::Reject));
+    assert!(matches!(node.handle_gossip_message(&block_message), gossipsub::MessageAcceptance::Reject));
+}
+
+#[tokio::test]
+async fn test_gossiped_transactions_are_validated() {
+    let mut node = Node::new().await;
+
+    let (alice_pub_key, alice_sec_key) = crypto::generate_keypair();
+    let (_bob_pub_key, bob_sec_key) = crypto::generate_keypair();
+    let initial_so = StateObject::new(alice_pub_key, vec![100], vec![]);
+    let initial_so_id = initial_so.id;
+    node.state_db.add_so(initial_so).unwrap();
+
+    // Bob tries to spend Alice's asset: this is a provably invalid message.
+    let mut forged_tx = Transaction::new(vec![initial_so_id], vec![], vec![]);
+    forged_tx.sign(sign_data(&forged_tx.id, &bob_sec_key));
+    let forged = p2p::encode_transaction(node.chain_id, &forged_tx);
+    let message = gossip_message(topics::transactions_topic(), forged);
+    assert!(matches!(node.handle_gossip_message(&message), gossipsub::MessageAcceptance::Reject));
+
+    // Alice's own transaction is accepted and lands in the mempool.
+    let mut tx = Transaction::new(vec![initial_so_id], vec![], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec_key));
+    let valid = p2p::encode_transaction(node.chain_id, &tx);
+    let message = gossip_message(topics::transactions_topic(), valid);
+    assert!(matches!(node.handle_gossip_message(&message), gossipsub::MessageAcceptance::Accept));
+    assert_eq!(node.mempool.get_batch(10).len(), 1);
+}
+
+#[tokio::test]
+async fn test_conflicting_blocks_are_rejected_without_changing_state() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 100);
+    node.state_db.add_so(funds.clone()).unwrap();
+    let spend = |inputs: Vec<_>, outputs: Vec<StateObject>| {
+        let mut tx = Transaction::new(inputs, outputs, vec![]);
+        tx.sign(sign_data(&tx.id, &alice_sec));
+        tx
+    };
+    let gossip_block = |node: &mut Node, transactions| {
+        let block = Block::new(node.chain.get_latest_hash(), alice, transactions, vec![]);
+        let message = gossip_message(topics::blocks_topic(), p2p::encode_block(node.chain_id, &block));
+        node.handle_gossip_message(&message)
+    };
+
+    // The same input twice in one transaction.
+    let twice = spend(vec![funds.id, funds.id], vec![]);
+    assert!(matches!(gossip_block(&mut node, vec![twice]), gossipsub::MessageAcceptance::Reject));
+
+    // Two transactions spending the same input.
+    let first = spend(vec![funds.id], vec![StateObject::new_value(alice, 60)]);
+    let second = spend(vec![funds.id], vec![StateObject::new_value(alice, 70)]);
+    assert!(matches!(gossip_block(&mut node, vec![first, second]), gossipsub::MessageAcceptance::Reject));
+
+    // An output that already exists.
+    let existing = StateObject::new_value(alice, 5);
+    node.state_db.add_so(existing.clone()).unwrap();
+    let recreate = spend(vec![funds.id], vec![existing]);
+    assert!(matches!(gossip_block(&mut node, vec![recreate]), gossipsub::MessageAcceptance::Reject));
+
+    assert_eq!(node.chain.height(), 0);
+    assert!(node.state_db.get_so(&funds.id).is_ok());
+    let valid = spend(vec![funds.id], vec![StateObject::new_value(alice, 60)]);
+    assert!(matches!(gossip_block(&mut node, vec![valid]), gossipsub::MessageAcceptance::Accept));
+}
+
+#[tokio::test]
+async fn test_transactions_that_depend_on_spent_objects_are_ignored() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 100);
+    node.state_db.add_so(funds.clone()).unwrap();
+    let gossip_transaction = |node: &mut Node, tx: &Transaction| {
+        let message = gossip_message(topics::transactions_topic(), p2p::encode_transaction(node.chain_id, tx));
+        node.handle_gossip_message(&message)
+    };
+
+    // The sender may have seen the referenced object before a block consumed it.
+    let consumed = StateObject::new_value(PublicKey([7; 32]), 1);
+    let mut reads = Transaction::with_reference_inputs(vec![funds.id], vec![consumed.id], vec![], vec![]);
+    reads.sign(sign_data(&reads.id, &alice_sec));
+    assert!(matches!(gossip_transaction(&mut node, &reads), gossipsub::MessageAcceptance::Ignore));
+
+    // Likewise for the target of a causal link.
+    let link = CausalLink { source_so_id: funds.id, target_so_id: consumed.id };
+    let mut links = Transaction::new(vec![funds.id], vec![], vec![link]);
+    links.sign(sign_data(&links.id, &alice_sec));
+    assert!(matches!(gossip_transaction(&mut node, &links), gossipsub::MessageAcceptance::Ignore));
+}
+
+#[tokio::test]
+async fn test_script_rejections_are_not_held_against_the_sender() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    // Spendable from height 10 on; a peer ahead of us may already relay its spend.
+    let logic = zelealem_node::asm::assemble("block_height\npush 9\ngt\nhalt").unwrap();
+    let locked = StateObject::new(alice, 50u64.to_le_bytes().to_vec(), logic);
+    node.state_db.add_so(locked.clone()).unwrap();
+
+    let mut tx = Transaction::new(vec![locked.id], vec![StateObject::new_value(alice, 50)], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec));
+    let message = gossip_message(topics::transactions_topic(), p2p::encode_transaction(node.chain_id, &tx));
+    assert!(matches!(node.handle_gossip_message(&message), gossipsub::MessageAcceptance::Ignore));
+    assert!(node.mempool.is_empty());
+}
@@ -0,0 +1,143 @@
+use zelealem_node::{
+    bytecode::OpCode,
+    crypto::{self, sign_data, PublicKey},
+    ledger::{Block, CausalLink, StateObject, Transaction},
+    node::{Node, ProcessBlockError},
+    state_db::StateDB,
+    validator::{TransactionValidator, ValidationError},
+};
+
+// An oracle object publishing a price. Nobody but the oracle could spend it,
+// but anyone may read it.
+fn oracle(price: u64) -> StateObject {
+    StateObject::new_value(PublicKey([0x0c; 32]), price)
+}
+
+// Spends `input`, reading `references`.
+fn reading(input: &StateObject, secret: &[u8], references: Vec<[u8; 32]>, links: Vec<CausalLink>) -> Transaction {
+    let mut tx = Transaction::with_reference_inputs(vec![input.id], references, vec![], links);
+    tx.sign(sign_data(&tx.id, secret));
+    tx
+}
+
+#[test]
+fn test_reference_inputs_must_exist_and_not_be_spent() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 10);
+    let price = oracle(42);
+    let mut state = StateDB::new();
+    state.add_so(funds.clone()).unwrap();
+    state.add_so(price.clone()).unwrap();
+
+    // Reading needs no signature from the referenced object's owner.
+    let tx = reading(&funds, &alice_sec, vec![price.id], vec![]);
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    let missing = oracle(7);
+    let tx = reading(&funds, &alice_sec, vec![missing.id], vec![]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::ReferenceNotFound(missing.id))
+    );
+
+    let tx = reading(&funds, &alice_sec, vec![funds.id], vec![]);
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::ReferenceAlsoSpent(funds.id))
+    );
+}
+
+#[test]
+fn test_scripts_can_read_reference_inputs() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 10);
+    // Accepts links while the first reference input holds a non-zero value: PUSH 0, REFERENCE, HALT.
+    let mut logic = vec![OpCode::Push as u8];
+    logic.extend_from_slice(&0i64.to_le_bytes());
+    logic.extend_from_slice(&[OpCode::Reference as u8, OpCode::Halt as u8]);
+    let market = StateObject::new(PublicKey([0x0d; 32]), b"market".to_vec(), logic);
+    let (open, closed) = (oracle(42), oracle(0));
+    let mut state = StateDB::new();
+    for so in [&funds, &market, &open, &closed] {
+        state.add_so(so.clone()).unwrap();
+    }
+    let link = || {
+        vec![CausalLink {
+            source_so_id: funds.id,
+            target_so_id: market.id,
+        }]
+    };
+
+    let tx = reading(&funds, &alice_sec, vec![open.id], link());
+    assert_eq!(TransactionValidator::new(&state).validate_transaction(&tx), Ok(()));
+
+    let tx = reading(&funds, &alice_sec, vec![closed.id], link());
+    assert_eq!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::CausalLinkRejected(market.id))
+    );
+
+    let tx = reading(&funds, &alice_sec, vec![], link());
+    assert!(matches!(
+        TransactionValidator::new(&state).validate_transaction(&tx),
+        Err(ValidationError::ScriptFailed { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_referenced_objects_stay_in_state() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let alice_funds = StateObject::new_value(alice, 10);
+    let bob_funds = StateObject::new_value(bob, 20);
+    let price = oracle(42);
+    for so in [&alice_funds, &bob_funds, &price] {
+        node.state_db.add_so(so.clone()).unwrap();
+    }
+
+    // Two users read the same object in the same block without conflicting.
+    let txs = vec![
+        reading(&alice_funds, &alice_sec, vec![price.id], vec![]),
+        reading(&bob_funds, &bob_sec, vec![price.id], vec![]),
+    ];
+    let block = Block::new(node.chain.get_latest_hash(), alice, txs, vec![]);
+    node.process_block(block).unwrap();
+
+    assert!(node.state_db.get_so(&alice_funds.id).is_err());
+    assert!(node.state_db.get_so(&bob_funds.id).is_err());
+    assert!(node.state_db.get_so(&price.id).is_ok());
+}
+
+#[tokio::test]
+async fn test_objects_spent_earlier_in_a_block_cannot_be_referenced() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let (bob, bob_sec) = crypto::generate_keypair();
+    let alice_funds = StateObject::new_value(alice, 10);
+    let bob_funds = StateObject::new_value(bob, 20);
+    for so in [&alice_funds, &bob_funds] {
+        node.state_db.add_so(so.clone()).unwrap();
+    }
+
+    // Alice spends her funds, then Bob reads them: each is valid on its own,
+    // against the state before the block.
+    let txs = vec![
+        reading(&alice_funds, &alice_sec, vec![], vec![]),
+        reading(&bob_funds, &bob_sec, vec![alice_funds.id], vec![]),
+    ];
+    let block = Block::new(node.chain.get_latest_hash(), alice, txs, vec![]);
+    assert!(matches!(
+        node.process_block(block),
+        Err(ProcessBlockError::SpentReference(id)) if id == alice_funds.id
+    ));
+    assert_eq!(node.chain.height(), 0);
+
+    // Read first, then spent, is fine.
+    let txs = vec![
+        reading(&bob_funds, &bob_sec, vec![alice_funds.id], vec![]),
+        reading(&alice_funds, &alice_sec, vec![], vec![]),
+    ];
+    let block = Block::new(node.chain.get_latest_hash(), alice, txs, vec![]);
+    node.process_block(block).unwrap();
+}
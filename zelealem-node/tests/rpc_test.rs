@@ -0,0 +1,133 @@
+use jsonrpsee::core::client::{ClientT, Subscription, SubscriptionClientT};
+use jsonrpsee::http_client::HttpClientBuilder;
+use jsonrpsee::rpc_params;
+use jsonrpsee::ws_client::WsClientBuilder;
+use std::net::SocketAddr;
+use tokio::sync::mpsc;
+use zelealem_node::{
+    crypto::{self, sign_data, PublicKey},
+    ledger::{Block, StateObject, Transaction},
+    node::Node,
+    p2p::DEFAULT_CHAIN_ID,
+    rpc::{self, BlockView, ChainTip, MempoolStatus, NodeQuery, StateObjectView},
+};
+
+// Starts a node and an RPC server in front of it, returning the server address and
+// a channel for running closures against the node, just as the event loop does.
+async fn start_node(setup: impl FnOnce(&mut Node)) -> (SocketAddr, mpsc::Sender<NodeQuery>, jsonrpsee::server::ServerHandle) {
+    let mut node = Node::new().await;
+    setup(&mut node);
+
+    let (query_tx, mut queries) = mpsc::channel::<NodeQuery>(16);
+    let ctx = rpc::RpcContext::new(query_tx.clone(), node.block_notifier.clone(), node.chain_id);
+    let (addr, handle) = rpc::start_server("127.0.0.1:0".parse().unwrap(), ctx).await.unwrap();
+
+    tokio::spawn(async move {
+        while let Some(query) = queries.recv().await {
+            query(&mut node);
+        }
+    });
+    (addr, query_tx, handle)
+}
+
+fn encode_tx(tx: &Transaction) -> String {
+    hex::encode(bincode::serde::encode_to_vec(tx, bincode::config::standard()).unwrap())
+}
+
+#[tokio::test]
+async fn test_queries_and_submission() {
+    let (alice_pub_key, alice_sec_key) = crypto::generate_keypair();
+    let initial_so = StateObject::new(alice_pub_key, vec![100], vec![]);
+    let initial_so_id = initial_so.id;
+
+    let (addr, _queries, _server) = start_node(|node| node.state_db.add_so(initial_so).unwrap()).await;
+    let client = HttpClientBuilder::default().build(format!("http://{}", addr)).unwrap();
+
+    let tip: ChainTip = client.request("zel_chainTip", rpc_params![]).await.unwrap();
+    assert_eq!(tip.height, 0);
+
+    let genesis: Option<BlockView> = client.request("zel_getBlockByHeight", rpc_params![0]).await.unwrap();
+    assert_eq!(genesis.unwrap().id, tip.hash);
+    let by_hash: Option<BlockView> = client.request("zel_getBlockByHash", rpc_params![tip.hash.clone()]).await.unwrap();
+    assert_eq!(by_hash.unwrap().height, 0);
+    let missing: Option<BlockView> = client.request("zel_getBlockByHeight", rpc_params![5]).await.unwrap();
+    assert!(missing.is_none());
+
+    let so: Option<StateObjectView> = client
+        .request("zel_getStateObject", rpc_params![hex::encode(initial_so_id)])
+        .await
+        .unwrap();
+    assert_eq!(so.unwrap().data, "64");
+    let alice_address = alice_pub_key.to_address(DEFAULT_CHAIN_ID);
+    let owned: Vec<StateObjectView> = client
+        .request("zel_getObjectsByOwner", rpc_params![alice_address.clone()])
+        .await
+        .unwrap();
+    assert_eq!(owned.len(), 1);
+    assert_eq!(owned[0].owner, alice_address);
+    let balance: u64 = client
+        .request("zel_getBalance", rpc_params![alice_address.clone()])
+        .await
+        .unwrap();
+    assert_eq!(balance, 0); // A one-byte data blob is not a value object.
+
+    // Addresses for another chain, raw hex keys and typos are all refused.
+    let chain_id: u32 = client.request("zel_chainId", rpc_params![]).await.unwrap();
+    assert_eq!(chain_id, DEFAULT_CHAIN_ID);
+    let mut typo = alice_address.clone();
+    typo.replace_range(10..11, if &typo[10..11] == "q" { "p" } else { "q" });
+    for bad in [alice_pub_key.to_address(7), hex::encode(alice_pub_key.0), typo] {
+        let err = client.request::<u64, _>("zel_getBalance", rpc_params![bad]).await;
+        assert!(matches!(err, Err(jsonrpsee::core::ClientError::Call(e)) if e.code() == -32602));
+    }
+
+    // A forged transaction is refused with a dedicated error code.
+    let (_bob_pub_key, bob_sec_key) = crypto::generate_keypair();
+    let mut forged = Transaction::new(vec![initial_so_id], vec![], vec![]);
+    forged.sign(sign_data(&forged.id, &bob_sec_key));
+    let err = client
+        .request::<String, _>("zel_submitTransaction", rpc_params![encode_tx(&forged)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, jsonrpsee::core::ClientError::Call(e) if e.code() == rpc::INVALID_TRANSACTION));
+
+    // Alice's transaction goes into the mempool.
+    let mut tx = Transaction::new(vec![initial_so_id], vec![], vec![]);
+    tx.sign(sign_data(&tx.id, &alice_sec_key));
+    let id: String = client.request("zel_submitTransaction", rpc_params![encode_tx(&tx)]).await.unwrap();
+    assert_eq!(id, hex::encode(tx.id));
+
+    let mempool: MempoolStatus = client.request("zel_mempoolStatus", rpc_params![]).await.unwrap();
+    assert_eq!(mempool.size, 1);
+
+    let err = client.request::<ChainTip, _>("zel_getBlockByHash", rpc_params!["zz"]).await;
+    assert!(err.is_err());
+}
+
+#[tokio::test]
+async fn test_new_block_subscription() {
+    let (addr, queries, _server) = start_node(|_| {}).await;
+    let client = WsClientBuilder::default().build(format!("ws://{}", addr)).await.unwrap();
+
+    let mut blocks: Subscription<BlockView> = client
+        .subscribe("zel_subscribeNewBlocks", rpc_params![], "zel_unsubscribeNewBlocks")
+        .await
+        .unwrap();
+
+    // Have the node append a block, as the event loop would after a proposer tick.
+    let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+    queries
+        .send(Box::new(move |node: &mut Node| {
+            let block = Block::new(node.chain.get_latest_hash(), PublicKey([1; 32]), vec![], vec![]);
+            let id = block.id;
+            node.process_block(block).unwrap();
+            let _ = done_tx.send(id);
+        }))
+        .await
+        .unwrap();
+    let block_id = done_rx.await.unwrap();
+
+    let announced = blocks.next().await.unwrap().unwrap();
+    assert_eq!(announced.id, hex::encode(block_id));
+    assert_eq!(announced.height, 1);
+}
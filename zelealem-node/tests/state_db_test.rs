@@ -0,0 +1,72 @@
+use zelealem_node::{
+    crypto::{self, PublicKey},
+    ledger::StateObject,
+    state_db::StateDB,
+};
+
+#[test]
+fn test_owner_index_follows_adds_and_removes() {
+    let mut state = StateDB::new();
+    let alice = PublicKey([0xaa; 32]);
+    let bob = PublicKey([0xbb; 32]);
+
+    let coin_a = StateObject::new_value(alice, 40);
+    let coin_b = StateObject::new_value(alice, 2);
+    let note = StateObject::new(alice, b"hello".to_vec(), vec![]);
+    let bob_coin = StateObject::new_value(bob, 7);
+    let (coin_a_id, note_id) = (coin_a.id, note.id);
+
+    for so in [coin_a, coin_b, note, bob_coin] {
+        state.add_so(so).unwrap();
+    }
+
+    assert_eq!(state.objects_by_owner(&alice).len(), 3);
+    assert_eq!(state.objects_by_owner(&bob).len(), 1);
+    assert!(state.objects_by_owner(&PublicKey([0; 32])).is_empty());
+
+    // The note isn't a value object, so it doesn't count towards the balance.
+    assert_eq!(state.balance_of(&alice), 42);
+    assert_eq!(state.balance_of(&bob), 7);
+
+    state.remove_so(&coin_a_id).unwrap();
+    state.remove_so(&note_id).unwrap();
+    assert_eq!(state.objects_by_owner(&alice).len(), 1);
+    assert_eq!(state.balance_of(&alice), 2);
+
+    // A failed add leaves the indexes untouched.
+    let duplicate = StateObject::new_value(bob, 7);
+    assert!(state.add_so(duplicate).is_err());
+    assert_eq!(state.objects_by_owner(&bob).len(), 1);
+}
+
+#[test]
+fn test_logic_index() {
+    let mut state = StateDB::new();
+    let logic = vec![0x01, 0x02, 0x03];
+    let logic_hash = crypto::hash_data(&logic);
+
+    let first = StateObject::new(PublicKey([1; 32]), vec![1], logic.clone());
+    let second = StateObject::new(PublicKey([2; 32]), vec![2], logic);
+    let plain = StateObject::new_value(PublicKey([3; 32]), 5);
+    let first_id = first.id;
+    for so in [first, second, plain] {
+        state.add_so(so).unwrap();
+    }
+
+    assert_eq!(state.objects_by_logic(&logic_hash).len(), 2);
+    assert_eq!(state.objects_by_logic(&crypto::hash_data(&[])).len(), 1);
+
+    state.remove_so(&first_id).unwrap();
+    assert_eq!(state.objects_by_logic(&logic_hash).len(), 1);
+}
+
+#[test]
+fn test_amount_requires_plain_value_object() {
+    let owner = PublicKey([1; 32]);
+    assert_eq!(StateObject::new_value(owner, 1234).amount(), Some(1234));
+    let salted = StateObject::new_salted_value(owner, 1234, [7; 32]);
+    assert_eq!(salted.amount(), Some(1234));
+    assert_ne!(salted.id, StateObject::new_value(owner, 1234).id);
+    assert_eq!(StateObject::new(owner, vec![100], vec![]).amount(), None);
+    assert_eq!(StateObject::new(owner, 5u64.to_le_bytes().to_vec(), vec![0x00]).amount(), None);
+}
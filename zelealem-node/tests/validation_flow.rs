@@ -141,4 +141,65 @@ async fn test_node_processes_valid_block() { // CORRECTED: Add the async keyword
     assert!(node.state_db.get_so(&initial_so_id).is_err());
     assert!(node.state_db.get_so(&new_so_id).is_ok());
     assert_ne!(node.chain.get_latest_hash(), latest_hash);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_conflicting_submissions_and_proposals() {
+    use zelealem_node::{node::SubmitError, validator::ValidationError};
+
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 100);
+    node.state_db.add_so(funds.clone()).unwrap();
+    let spend = |inputs: Vec<_>, amount| {
+        let mut tx = Transaction::new(inputs, vec![StateObject::new_value(alice, amount)], vec![]);
+        tx.sign(sign_data(&tx.id, &alice_sec));
+        tx
+    };
+
+    // Only the first of two transactions spending the same object gets into the mempool.
+    node.submit_transaction(spend(vec![funds.id], 60)).unwrap();
+    assert_eq!(node.submit_transaction(spend(vec![funds.id], 70)), Err(SubmitError::Conflict(funds.id)));
+    assert_eq!(
+        node.submit_transaction(spend(vec![funds.id, funds.id], 80)),
+        Err(SubmitError::Invalid(ValidationError::DuplicateInput(funds.id)))
+    );
+    assert_eq!(node.mempool.len(), 1);
+
+    // Should conflicting transactions reach the mempool anyway, the proposer keeps only the first.
+    node.mempool.add_transaction(spend(vec![funds.id], 70));
+    let block = node.propose_block(alice, 10);
+    assert_eq!(block.transactions.len(), 1);
+    assert_eq!(block.transactions[0].outputs[0].amount(), Some(60));
+    node.process_block(block).unwrap();
+    assert!(node.mempool.is_empty());
+}
+
+#[tokio::test]
+async fn test_applied_blocks_evict_mempool_transactions() {
+    let mut node = Node::new().await;
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let funds = StateObject::new_value(alice, 100);
+    let savings = StateObject::new_value(alice, 200);
+    node.state_db.add_so(funds.clone()).unwrap();
+    node.state_db.add_so(savings.clone()).unwrap();
+    let spend = |input: &StateObject, amount| {
+        let mut tx = Transaction::new(vec![input.id], vec![StateObject::new_value(alice, amount)], vec![]);
+        tx.sign(sign_data(&tx.id, &alice_sec));
+        tx
+    };
+
+    // A relay node never proposes, so only applying blocks can clear its mempool.
+    let included = spend(&funds, 60);
+    node.submit_transaction(included.clone()).unwrap();
+    node.submit_transaction(spend(&savings, 150)).unwrap();
+    let block = Block::new(node.chain.get_latest_hash(), alice, vec![included], vec![]);
+    node.process_block(block).unwrap();
+    assert_eq!(node.mempool.len(), 1);
+
+    // A block spending the same input as a pooled transaction evicts it, and frees the input.
+    let conflicting = spend(&savings, 180);
+    let block = Block::new(node.chain.get_latest_hash(), alice, vec![conflicting.clone()], vec![]);
+    node.process_block(block).unwrap();
+    assert!(node.mempool.is_empty());
+    assert_eq!(node.mempool.conflicting_input(&conflicting), None);
+}
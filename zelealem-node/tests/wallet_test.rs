@@ -0,0 +1,156 @@
+use zelealem_node::{
+    crypto::{self, PublicKey},
+    ledger::{StateObject, Transaction},
+    p2p::DEFAULT_CHAIN_ID,
+    rpc::{StateObjectView, ViewError},
+    state_db::StateDB,
+    validator::TransactionValidator,
+    wallet::{self, PartialTransaction, WalletError},
+};
+
+#[test]
+fn test_transfer_selects_inputs_and_returns_change() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let bob = PublicKey([0xbb; 32]);
+
+    let owned = vec![
+        StateObject::new_value(alice, 10),
+        StateObject::new_value(alice, 50),
+        StateObject::new_value(alice, 30),
+        StateObject::new(alice, b"not money".to_vec(), vec![]),
+    ];
+    let mut state = StateDB::new();
+    for so in &owned {
+        state.add_so(so.clone()).unwrap();
+    }
+
+    let mut tx = wallet::build_transfer(&owned, bob, 70, alice).unwrap();
+
+    // Largest first: 50 + 30 covers 70, leaving 10 change.
+    assert_eq!(tx.inputs, vec![owned[1].id, owned[2].id]);
+    assert_eq!(tx.outputs.len(), 2);
+    assert_eq!((tx.outputs[0].owner, tx.outputs[0].amount()), (bob, Some(70)));
+    assert_eq!((tx.outputs[1].owner, tx.outputs[1].amount()), (alice, Some(10)));
+
+    wallet::sign_transaction(&mut tx, &owned, &alice_sec).unwrap();
+    assert!(TransactionValidator::new(&state).validate_transaction(&tx).is_ok());
+}
+
+#[test]
+fn test_exact_transfer_has_no_change() {
+    let alice = PublicKey([0xaa; 32]);
+    let owned = vec![StateObject::new_value(alice, 25)];
+    let tx = wallet::build_transfer(&owned, PublicKey([0xbb; 32]), 25, alice).unwrap();
+    assert_eq!(tx.outputs.len(), 1);
+}
+
+#[test]
+fn test_transfer_errors() {
+    let alice = PublicKey([0xaa; 32]);
+    let owned = vec![StateObject::new_value(alice, 5), StateObject::new_value(alice, 6)];
+
+    assert!(matches!(
+        wallet::build_transfer(&owned, alice, 12, alice),
+        Err(WalletError::InsufficientFunds { needed: 12, available: 11 })
+    ));
+    assert!(matches!(wallet::build_transfer(&owned, alice, 0, alice), Err(WalletError::ZeroAmount)));
+
+    // The wallet refuses to sign for objects it doesn't know it owns.
+    let mut tx = wallet::build_transfer(&owned, alice, 5, alice).unwrap();
+    assert_eq!(tx.inputs, vec![owned[1].id]);
+    assert!(matches!(
+        wallet::sign_transaction(&mut tx, &owned[..1], &[0; 32]),
+        Err(WalletError::UnknownInput(_))
+    ));
+}
+
+#[test]
+fn test_state_object_view_round_trip() {
+    let so = StateObject::new(PublicKey([3; 32]), vec![1, 2], vec![0]);
+    let view = StateObjectView::new(&so, DEFAULT_CHAIN_ID);
+    assert_eq!(view.to_state_object(DEFAULT_CHAIN_ID).unwrap().id, so.id);
+
+    // An owner on another chain is rejected.
+    assert!(matches!(view.to_state_object(2), Err(ViewError::InvalidOwner(_))));
+
+    // A node can't alter an object's content without the id giving it away.
+    let mut tampered = view.clone();
+    tampered.data = "0203".to_string();
+    assert_eq!(tampered.to_state_object(DEFAULT_CHAIN_ID).unwrap_err(), ViewError::MismatchedId);
+}
+
+#[test]
+fn test_offline_signing_round_trip() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let owned = vec![StateObject::new_value(alice, 40), StateObject::new_value(alice, 15)];
+    let mut state = StateDB::new();
+    for so in &owned {
+        state.add_so(so.clone()).unwrap();
+    }
+
+    // Online machine: build the transfer and export it with the objects it spends.
+    let tx = wallet::build_transfer(&owned, PublicKey([0xbb; 32]), 45, alice).unwrap();
+    let exported = PartialTransaction::new(tx, owned.clone(), DEFAULT_CHAIN_ID).unwrap().encode();
+
+    // Offline machine: review and sign, with nothing but the exported text.
+    let mut partial = PartialTransaction::decode(&exported).unwrap();
+    assert_eq!(partial.value_in_out(), (55, 55));
+    assert!(matches!(partial.clone().finalize(DEFAULT_CHAIN_ID), Err(WalletError::Incomplete(_))));
+    partial.sign(&alice_sec).unwrap();
+    let signed = partial.encode();
+
+    // Online machine again: finalize and check it is what the node will accept.
+    let tx = PartialTransaction::decode(&signed).unwrap().finalize(DEFAULT_CHAIN_ID).unwrap();
+    assert!(TransactionValidator::new(&state).validate_transaction(&tx).is_ok());
+    assert!(matches!(
+        PartialTransaction::decode(&signed).unwrap().finalize(2),
+        Err(WalletError::WrongChain { expected: 2, found: DEFAULT_CHAIN_ID })
+    ));
+}
+
+#[test]
+fn test_partial_transaction_rejects_tampering() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let owned = vec![StateObject::new_value(alice, 40)];
+    let tx = wallet::build_transfer(&owned, PublicKey([0xbb; 32]), 10, alice).unwrap();
+    let partial = PartialTransaction::new(tx, owned.clone(), DEFAULT_CHAIN_ID).unwrap();
+
+    // Redirecting an output invalidates the transaction id.
+    let mut redirected = partial.clone();
+    redirected.transaction.outputs[0].owner = PublicKey([0xee; 32]);
+    assert!(matches!(redirected.sign(&alice_sec), Err(WalletError::MalformedPartial(_))));
+
+    // Inflating an input's value for review invalidates the input's id.
+    let mut inflated = partial.clone();
+    inflated.input_objects[0].data = 1000u64.to_le_bytes().to_vec();
+    assert!(matches!(PartialTransaction::decode(&inflated.encode()), Err(WalletError::MalformedPartial(_))));
+
+    // Swapping in a different, self-consistent object is caught too.
+    let mut swapped = partial.clone();
+    swapped.input_objects[0] = StateObject::new_value(alice, 1000);
+    assert!(matches!(swapped.verify(), Err(WalletError::MalformedPartial(_))));
+
+    // Only the owner of the inputs may sign.
+    let (_, mallory_sec) = crypto::generate_keypair();
+    assert!(matches!(partial.clone().sign(&mallory_sec), Err(WalletError::CannotSign)));
+
+    assert!(matches!(PartialTransaction::decode("not hex"), Err(WalletError::MalformedPartial(_))));
+}
+
+#[test]
+fn test_partial_transaction_with_reference_inputs() {
+    let (alice, alice_sec) = crypto::generate_keypair();
+    let owned = vec![StateObject::new_value(alice, 40)];
+    let oracle = StateObject::new(PublicKey([0xcc; 32]), b"price".to_vec(), vec![]);
+    let mut state = StateDB::new();
+    state.add_so(owned[0].clone()).unwrap();
+    state.add_so(oracle.clone()).unwrap();
+
+    let transfer = wallet::build_transfer(&owned, PublicKey([0xbb; 32]), 40, alice).unwrap();
+    let tx = Transaction::with_reference_inputs(transfer.inputs, vec![oracle.id], transfer.outputs, vec![]);
+    let mut partial = PartialTransaction::new(tx, owned, DEFAULT_CHAIN_ID).unwrap();
+    partial.sign(&alice_sec).unwrap();
+    let tx = partial.finalize(DEFAULT_CHAIN_ID).unwrap();
+    assert_eq!(tx.reference_inputs, vec![oracle.id]);
+    assert!(TransactionValidator::new(&state).validate_transaction(&tx).is_ok());
+}
@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+use zelealem_node::{
+    crypto::PublicKey,
+    ledger::{Block, CausalLink, MultisigPolicy, StateObject, Transaction},
+    p2p::{self, Envelope, EnvelopeError, MessageKind, PROTOCOL_VERSION},
+};
+
+// The golden fixtures in `tests/fixtures` pin the exact bytes we put on the wire.
+// If one of these tests fails, a change has broken interop with deployed nodes:
+// either revert it or bump `PROTOCOL_VERSION`. After an intentional format change,
+// regenerate the fixtures with `ZELEALEM_BLESS=1 cargo test --test wire_protocol`.
+
+const CHAIN_ID: p2p::ChainId = 7;
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn check_golden(name: &str, actual: &[u8]) {
+    let path = fixture_path(name);
+    if std::env::var_os("ZELEALEM_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", name, e));
+    assert_eq!(actual, &expected[..], "wire encoding of {} changed", name);
+}
+
+// A fully deterministic transaction: fixed keys, data and signature.
+fn sample_transaction() -> Transaction {
+    let alice = PublicKey([0xaa; 32]);
+    let output = StateObject::new(alice, vec![1, 2, 3], vec![0x00]);
+    let link = CausalLink {
+        source_so_id: [0x11; 32],
+        target_so_id: [0x22; 32],
+    };
+    let mut tx = Transaction::with_reference_inputs(vec![[0x33; 32]], vec![[0x34; 32]], vec![output], vec![link]);
+    tx.set_policy(0, MultisigPolicy::new(1, vec![PublicKey([0x77; 32]), PublicKey([0x88; 32])]).unwrap());
+    tx.sign([0x44; 64]);
+    tx.sign([0x45; 64]);
+    tx
+}
+
+fn sample_block() -> Block {
+    Block::new([0x55; 32], PublicKey([0x66; 32]), vec![sample_transaction()], vec![9, 9])
+}
+
+#[test]
+fn test_transaction_golden_bytes() {
+    let bytes = p2p::encode_transaction(CHAIN_ID, &sample_transaction());
+    check_golden("transaction_v4.bin", &bytes);
+
+    let decoded = p2p::decode_transaction(&bytes, CHAIN_ID).unwrap();
+    assert_eq!(decoded.id, sample_transaction().id);
+}
+
+#[test]
+fn test_block_golden_bytes() {
+    let bytes = p2p::encode_block(CHAIN_ID, &sample_block());
+    check_golden("block_v4.bin", &bytes);
+
+    let decoded = p2p::decode_block(&bytes, CHAIN_ID).unwrap();
+    assert_eq!(decoded.id, sample_block().id);
+}
+
+#[test]
+fn test_envelope_header_layout() {
+    let bytes = Envelope::new(CHAIN_ID, MessageKind::Block, vec![0xde, 0xad]).encode();
+    assert_eq!(
+        bytes,
+        vec![
+            b'Z', b'L', b'M', 0x00, // magic
+            0x07, 0x00, 0x00, 0x00, // chain id
+            0x04, 0x00, // version
+            0x02, // kind
+            0x02, 0x00, 0x00, 0x00, // payload length
+            0xde, 0xad, // payload
+        ]
+    );
+}
+
+#[test]
+fn test_rejects_other_networks() {
+    let bytes = p2p::encode_transaction(CHAIN_ID, &sample_transaction());
+    assert_eq!(
+        p2p::decode_transaction(&bytes, CHAIN_ID + 1).unwrap_err(),
+        EnvelopeError::WrongChain { expected: CHAIN_ID + 1, found: CHAIN_ID }
+    );
+}
+
+#[test]
+fn test_rejects_malformed_envelopes() {
+    let good = p2p::encode_transaction(CHAIN_ID, &sample_transaction());
+
+    assert_eq!(Envelope::decode(&good[..10], CHAIN_ID).unwrap_err(), EnvelopeError::TooShort(10));
+
+    let mut bad_magic = good.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(Envelope::decode(&bad_magic, CHAIN_ID).unwrap_err(), EnvelopeError::BadMagic);
+
+    let mut future_version = good.clone();
+    future_version[8..10].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+    assert_eq!(
+        Envelope::decode(&future_version, CHAIN_ID).unwrap_err(),
+        EnvelopeError::UnsupportedVersion(PROTOCOL_VERSION + 1)
+    );
+
+    let mut unknown_kind = good.clone();
+    unknown_kind[10] = 0x7f;
+    assert_eq!(Envelope::decode(&unknown_kind, CHAIN_ID).unwrap_err(), EnvelopeError::UnknownKind(0x7f));
+
+    let truncated = &good[..good.len() - 1];
+    assert!(matches!(
+        Envelope::decode(truncated, CHAIN_ID).unwrap_err(),
+        EnvelopeError::LengthMismatch { .. }
+    ));
+
+    // A well-formed transaction envelope is not a block.
+    assert_eq!(
+        p2p::decode_block(&good, CHAIN_ID).unwrap_err(),
+        EnvelopeError::UnexpectedKind { expected: MessageKind::Block, found: MessageKind::Transaction }
+    );
+}
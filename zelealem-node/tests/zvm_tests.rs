@@ -1,6 +1,10 @@
+mod common;
+
+use common::assemble;
 use zelealem_node::{
+    asm,
     bytecode::OpCode,
-    zvm::ZVM,
+    zvm::{VmError, ZVM},
 };
 
 #[test]
@@ -70,6 +74,221 @@ fn test_stack_underflow_error() {
     let result = vm.run(); // We expect this to return an Err.
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), "ADD requires two values on the stack");
+    assert_eq!(result.unwrap_err(), VmError::StackUnderflow(OpCode::Add));
     println!("SUCCESS: ZVM correctly panicked on stack underflow.");
-}
\ No newline at end of file
+}
+#[test]
+fn test_link_source_needs_a_link() {
+    // LINKSOURCE only makes sense when the script runs as a causal link's target.
+    let mut vm = ZVM::new(vec![OpCode::LinkSource as u8, OpCode::Halt as u8]);
+    assert_eq!(vm.run().unwrap_err(), VmError::NoLinkSource);
+}
+
+#[test]
+fn test_malformed_bytecode_is_an_error() {
+    // Unknown opcodes are rejected when decoded.
+    assert_eq!(OpCode::try_from(0xff).unwrap_err(), VmError::InvalidOpcode(0xff));
+    assert_eq!(OpCode::try_from(OpCode::Sub as u8), Ok(OpCode::Sub));
+    assert_eq!(ZVM::new(vec![0xff]).run(), Err(VmError::InvalidOpcode(0xff)));
+
+    // A PUSH cut short, and code that never halts, fail instead of panicking.
+    assert_eq!(
+        ZVM::new(vec![OpCode::Push as u8, 1, 2, 3]).run(),
+        Err(VmError::TruncatedImmediate(OpCode::Push))
+    );
+    assert_eq!(ZVM::new(vec![]).run(), Err(VmError::PcOutOfBounds));
+    assert_eq!(ZVM::new(vec![OpCode::Halt as u8]).run(), Err(VmError::StackUnderflow(OpCode::Halt)));
+}
+
+#[test]
+fn test_arbitrary_bytes_never_panic() {
+    // A small xorshift generator keeps the test deterministic without extra dependencies.
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..2_000 {
+        let len = (seed % 32) as usize;
+        let code: Vec<u8> = (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                (seed % 0x33) as u8
+            })
+            .collect();
+        let _ = ZVM::new(code).run();
+    }
+}
+
+// Runs `a <opcode> b` and returns the result.
+fn eval(a: i64, opcode: OpCode, b: i64) -> Result<i64, VmError> {
+    ZVM::new(assemble(&format!("push {a}\npush {b}\n{}\nhalt", asm::mnemonic(opcode)))).run()
+}
+
+#[test]
+fn test_integer_opcodes() {
+    assert_eq!(eval(6, OpCode::Mul, -7), Ok(-42));
+    assert_eq!(eval(-7, OpCode::Div, 2), Ok(-3));
+    assert_eq!(eval(-7, OpCode::Mod, 2), Ok(-1));
+
+    assert_eq!(eval(3, OpCode::Eq, 3), Ok(1));
+    assert_eq!(eval(3, OpCode::Lt, 4), Ok(1));
+    assert_eq!(eval(3, OpCode::Gt, 4), Ok(0));
+
+    // Logical operators treat any non-zero value as true and produce 0 or 1.
+    assert_eq!(eval(5, OpCode::And, -1), Ok(1));
+    assert_eq!(eval(5, OpCode::And, 0), Ok(0));
+    assert_eq!(eval(0, OpCode::Or, 9), Ok(1));
+    assert_eq!(eval(1, OpCode::Not, 0), Ok(1)); // NOT applies to the top value only.
+
+    assert_eq!(eval(0b1100, OpCode::BitAnd, 0b1010), Ok(0b1000));
+    assert_eq!(eval(0b1100, OpCode::BitOr, 0b1010), Ok(0b1110));
+    assert_eq!(eval(0b1100, OpCode::BitXor, 0b1010), Ok(0b0110));
+    assert_eq!(eval(0, OpCode::BitNot, 0), Ok(-1));
+    assert_eq!(eval(3, OpCode::Shl, 4), Ok(48));
+    assert_eq!(eval(-16, OpCode::Shr, 2), Ok(-4));
+    assert_eq!(eval(-1, OpCode::Shl, 63), Ok(i64::MIN));
+}
+
+#[test]
+fn test_arithmetic_faults_instead_of_wrapping() {
+    assert_eq!(eval(i64::MAX, OpCode::Add, 1), Err(VmError::Overflow(OpCode::Add)));
+    assert_eq!(eval(i64::MIN, OpCode::Sub, 1), Err(VmError::Overflow(OpCode::Sub)));
+    assert_eq!(eval(i64::MAX, OpCode::Mul, 2), Err(VmError::Overflow(OpCode::Mul)));
+    assert_eq!(eval(i64::MIN, OpCode::Div, -1), Err(VmError::Overflow(OpCode::Div)));
+    assert_eq!(eval(1, OpCode::Div, 0), Err(VmError::DivisionByZero(OpCode::Div)));
+    assert_eq!(eval(1, OpCode::Mod, 0), Err(VmError::DivisionByZero(OpCode::Mod)));
+    assert_eq!(eval(1, OpCode::Shl, 64), Err(VmError::Overflow(OpCode::Shl)));
+    assert_eq!(eval(1, OpCode::Shl, -1), Err(VmError::Overflow(OpCode::Shl)));
+    assert_eq!(eval(1, OpCode::Shr, -1), Err(VmError::Overflow(OpCode::Shr)));
+    // Shifting set bits out, or into the sign bit, overflows like the matching Mul would.
+    assert_eq!(eval(i64::MAX, OpCode::Shl, 1), Err(VmError::Overflow(OpCode::Shl)));
+    assert_eq!(eval(1, OpCode::Shl, 63), Err(VmError::Overflow(OpCode::Shl)));
+    assert_eq!(eval(i64::MIN, OpCode::Shl, 1), Err(VmError::Overflow(OpCode::Shl)));
+}
+
+#[test]
+fn test_conditional_branches() {
+    let branch = |cond| {
+        let source = format!(
+            "
+                push {cond}
+                jump_if taken
+                push 10
+                halt
+            taken:
+                push 20
+                halt
+            "
+        );
+        ZVM::new(assemble(&source)).run()
+    };
+    assert_eq!(branch(1), Ok(20));
+    assert_eq!(branch(0), Ok(10));
+}
+
+#[test]
+fn test_call_and_return() {
+    let source = "
+            call f
+            push 1
+            add
+            halt
+        f:
+            push 41
+            return
+    ";
+    assert_eq!(ZVM::new(assemble(source)).run(), Ok(42));
+
+    assert_eq!(ZVM::new(vec![OpCode::Return as u8]).run(), Err(VmError::ReturnWithoutCall));
+
+    // Unbounded recursion hits the call depth limit.
+    assert_eq!(ZVM::new(assemble("f:\ncall f")).run(), Err(VmError::CallDepthExceeded));
+}
+
+#[test]
+fn test_infinite_loops_run_out_of_gas() {
+    assert_eq!(ZVM::new(assemble("top:\njump top")).run(), Err(VmError::OutOfGas));
+}
+
+#[test]
+fn test_jump_targets_are_checked_before_running() {
+    // Jumping into the middle of a PUSH immediate would execute data as code.
+    let code = assemble("push 0x18000000\njump 1");
+    assert_eq!(ZVM::new(code).run(), Err(VmError::InvalidJumpTarget { at: 9, target: 1 }));
+
+    // So is jumping past the end, even from code that is never reached.
+    let code = assemble("push 7\nhalt\njump 100");
+    assert_eq!(ZVM::new(code).run(), Err(VmError::InvalidJumpTarget { at: 10, target: 100 }));
+}
+
+#[test]
+fn test_stack_manipulation() {
+    let source = "
+        push 1
+        push 2
+        swap
+        sub     ; 2 - 1
+        dup
+        dup
+        pop
+        add     ; 1 + 1
+        halt
+    ";
+    assert_eq!(ZVM::new(assemble(source)).run(), Ok(2));
+
+    assert_eq!(ZVM::new(vec![OpCode::Dup as u8]).run(), Err(VmError::StackUnderflow(OpCode::Dup)));
+    assert_eq!(ZVM::new(vec![OpCode::Swap as u8]).run(), Err(VmError::StackUnderflow(OpCode::Swap)));
+    assert_eq!(ZVM::new(vec![OpCode::Pop as u8]).run(), Err(VmError::StackUnderflow(OpCode::Pop)));
+}
+
+#[test]
+fn test_max_stack_depth() {
+    let code = assemble("push 1\ndup\ndup\nhalt");
+    assert_eq!(ZVM::new(code.clone()).with_max_stack(3).run(), Ok(1));
+    assert_eq!(ZVM::new(code).with_max_stack(2).run(), Err(VmError::StackOverflow(2)));
+}
+
+#[test]
+fn test_loop_with_locals() {
+    let source = "
+        ; Sums 1..=10 with i in slot 0 and sum in slot 1.
+            push 10
+            store_local 0
+            push 0
+            store_local 1
+        top:
+            load_local 1    ; sum = sum + i
+            load_local 0
+            add
+            store_local 1
+            load_local 0    ; i = i - 1, looping while it is non-zero
+            push 1
+            sub
+            dup
+            store_local 0
+            jump_if top
+            load_local 1
+            halt
+    ";
+    assert_eq!(ZVM::new(assemble(source)).run(), Ok(55));
+}
+
+#[test]
+fn test_each_call_has_its_own_locals() {
+    let source = "
+            push 5
+            store_local 0
+            call f
+            load_local 0
+            add
+            halt
+        f:
+            load_local 0
+            push 7
+            store_local 0
+            return
+    ";
+    // The callee sees a fresh slot (0), and its store doesn't leak back: 0 + 5.
+    assert_eq!(ZVM::new(assemble(source)).run(), Ok(5));
+
+    assert_eq!(ZVM::new(assemble("load_local 16")).run(), Err(VmError::InvalidLocal(16)));
+}